@@ -0,0 +1,31 @@
+// Captures the build metadata printed by `sorastats --version --verbose`.
+
+const REPORTED_DEPENDENCIES: &[&str] = &["ratatui", "crossterm", "ureq", "serde_json"];
+
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    let target = std::env::var("TARGET").unwrap_or_else(|_| "unknown".to_owned());
+    println!("cargo:rustc-env=SORASTATS_BUILD_TARGET={target}");
+
+    let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let versions = REPORTED_DEPENDENCIES
+        .iter()
+        .map(|name| {
+            let version = locked_version(&lock, name).unwrap_or("unknown");
+            format!("{name} {version}")
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!("cargo:rustc-env=SORASTATS_BUILD_DEPENDENCIES={versions}");
+}
+
+fn locked_version<'a>(lock: &'a str, name: &str) -> Option<&'a str> {
+    let mut lines = lock.lines();
+    let name_line = format!("name = \"{name}\"");
+    lines.find(|line| *line == name_line)?;
+    lines
+        .next()?
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')
+}
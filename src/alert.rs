@@ -0,0 +1,119 @@
+use crate::stats::{ConnectionId, Stats, StatsItemKey};
+use std::time::SystemTime;
+
+/// Threshold predicate on a per-connection stats value (e.g., "rtp.packets_lost/s>10").
+#[derive(Debug, Clone)]
+pub struct AlertRule {
+    pub key: StatsItemKey,
+
+    /// If true, the predicate is evaluated on the delta per second instead of the value.
+    pub per_sec: bool,
+
+    pub op: AlertOp,
+    pub threshold: f64,
+}
+
+impl AlertRule {
+    pub fn evaluate<'a>(&'a self, stats: &'a Stats) -> impl 'a + Iterator<Item = Alert> {
+        stats.iter_connections().filter_map(move |conn| {
+            let item = conn.get(&self.key)?;
+            let value = if self.per_sec {
+                item.delta_per_sec?
+            } else {
+                item.value.as_f64()?
+            };
+            self.op.matches(value, self.threshold).then(|| Alert {
+                time: stats.time,
+                rule: self.clone(),
+                connection_id: conn.connection_id.clone(),
+                value,
+            })
+        })
+    }
+}
+
+impl std::str::FromStr for AlertRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (i, op) = [
+            (">=", AlertOp::Ge),
+            ("<=", AlertOp::Le),
+            (">", AlertOp::Gt),
+            ("<", AlertOp::Lt),
+        ]
+        .into_iter()
+        .find_map(|(token, op)| s.find(token).map(|i| (i, (token, op))))
+        .ok_or_else(|| format!("expected `KEY>THRESHOLD` format, but got {s:?}"))?;
+        let (token, op) = op;
+
+        let key = s[..i].trim();
+        let (key, per_sec) = if let Some(key) = key.strip_suffix("/s") {
+            (key, true)
+        } else {
+            (key, false)
+        };
+        if key.is_empty() {
+            return Err(format!("missing stats key in {s:?}"));
+        }
+        let threshold = s[i + token.len()..]
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid threshold in {s:?}: {e}"))?;
+        Ok(Self {
+            key: key.to_owned(),
+            per_sec,
+            op,
+            threshold,
+        })
+    }
+}
+
+impl std::fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.key)?;
+        if self.per_sec {
+            write!(f, "/s")?;
+        }
+        write!(f, "{}{}", self.op, self.threshold)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertOp {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl AlertOp {
+    fn matches(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Self::Gt => value > threshold,
+            Self::Ge => value >= threshold,
+            Self::Lt => value < threshold,
+            Self::Le => value <= threshold,
+        }
+    }
+}
+
+impl std::fmt::Display for AlertOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Gt => write!(f, ">"),
+            Self::Ge => write!(f, ">="),
+            Self::Lt => write!(f, "<"),
+            Self::Le => write!(f, "<="),
+        }
+    }
+}
+
+/// Connection that matches an alert rule.
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub time: SystemTime,
+    pub rule: AlertRule,
+    pub connection_id: ConnectionId,
+    pub value: f64,
+}
@@ -0,0 +1,79 @@
+use crate::stats::StatsItemKey;
+use std::collections::BTreeMap;
+
+const CONNECTION_ID_KEY: &str = "connection_id";
+
+/// Replaces connection IDs and the values of the specified string keys with stable pseudonyms.
+///
+/// The same original value is always mapped to the same pseudonym (e.g., "conn-0001"),
+/// so deltas and aggregations keyed by connection ID are not affected.
+#[derive(Debug, Default)]
+pub struct Anonymizer {
+    keys: Vec<StatsItemKey>,
+    pseudonyms: BTreeMap<(StatsItemKey, String), String>,
+    counts: BTreeMap<StatsItemKey, usize>,
+}
+
+impl Anonymizer {
+    pub fn new(keys: &[StatsItemKey]) -> Self {
+        Self {
+            keys: keys.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    pub fn anonymize_values(&mut self, values: &mut [serde_json::Value]) {
+        let mut key = String::new();
+        for value in values {
+            if let serde_json::Value::Object(obj) = value {
+                self.anonymize_object(obj, &mut key);
+            }
+        }
+    }
+
+    fn anonymize_object(
+        &mut self,
+        obj: &mut serde_json::Map<String, serde_json::Value>,
+        key: &mut String,
+    ) {
+        for (k, v) in obj {
+            let old_len = key.len();
+            if !key.is_empty() {
+                key.push('.');
+            }
+            key.push_str(k);
+            match v {
+                serde_json::Value::String(s) if self.is_target(key) => {
+                    *s = self.pseudonym(key, s);
+                }
+                serde_json::Value::Object(children) => {
+                    self.anonymize_object(children, key);
+                }
+                _ => {}
+            }
+            key.truncate(old_len);
+        }
+    }
+
+    fn is_target(&self, key: &str) -> bool {
+        key == CONNECTION_ID_KEY || self.keys.iter().any(|k| k == key)
+    }
+
+    fn pseudonym(&mut self, key: &str, value: &str) -> String {
+        let id = (key.to_owned(), value.to_owned());
+        if let Some(pseudonym) = self.pseudonyms.get(&id) {
+            return pseudonym.clone();
+        }
+
+        let count = self.counts.entry(key.to_owned()).or_default();
+        *count += 1;
+        let prefix = if key == CONNECTION_ID_KEY {
+            "conn"
+        } else {
+            key
+        };
+        let pseudonym = format!("{prefix}-{count:04}");
+        self.pseudonyms.insert(id, pseudonym.clone());
+        pseudonym
+    }
+}
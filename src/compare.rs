@@ -0,0 +1,127 @@
+use crate::poll;
+use crate::stats::{AggregatedStatsItemValue, ConnectionStats, Stats};
+use crate::Options;
+use orfail::OrFail;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::Duration;
+
+/// Snapshot of a record file picked for `--compare`.
+#[derive(Debug)]
+struct Snapshot {
+    stats: Stats,
+
+    /// Offset from the time of the first record in the file.
+    offset: Duration,
+}
+
+impl Snapshot {
+    fn load(path: &Path, options: &Options) -> orfail::Result<Self> {
+        let (_, items) = poll::read_record_file(path).or_fail()?;
+        let origin = items
+            .first()
+            .or_fail_with(|()| format!("no stats in record file {path:?}"))?
+            .time;
+
+        // Aligns the two files by the relative time from their first records.
+        let item = items
+            .into_iter()
+            .take_while(|item| {
+                options
+                    .replay_to
+                    .is_none_or(|to| item.time <= to.resolve(origin))
+            })
+            .last()
+            .or_fail_with(|()| format!("no stats within the replay range in {path:?}"))?;
+        let offset = item.time.duration_since(origin).unwrap_or_default();
+        let (connections, duplicate_connection_count) =
+            ConnectionStats::from_snapshot(item.values, &[], item.time)?;
+        let connections = connections
+            .into_iter()
+            .filter(|c| {
+                c.filter_targets()
+                    .any(|target| options.connection_filter.is_match(&target))
+            })
+            .collect();
+        let mut stats = Stats::new(item.time, offset, connections);
+        stats.duplicate_connection_count = duplicate_connection_count;
+        Ok(Self { stats, offset })
+    }
+}
+
+/// Prints a per-key delta table of the aggregated stats in the final snapshots of the two record files.
+pub fn run(options: &Options, path_a: &Path, path_b: &Path) -> orfail::Result<()> {
+    let a = Snapshot::load(path_a, options).or_fail()?;
+    let b = Snapshot::load(path_b, options).or_fail()?;
+    for (label, path, snapshot) in [("A", path_a, &a), ("B", path_b, &b)] {
+        println!(
+            "{label}: {} (+{}s, {} connections)",
+            path.display(),
+            snapshot.offset.as_secs(),
+            snapshot.stats.connection_count()
+        );
+    }
+    println!();
+
+    let keys = a
+        .stats
+        .aggregated
+        .filtered_items(&options.stats_key_filter)
+        .chain(b.stats.aggregated.filtered_items(&options.stats_key_filter))
+        .filter(|(_, v)| v.value_sum.is_some())
+        .map(|(k, _)| k.clone())
+        .collect::<BTreeSet<_>>();
+    let mut rows = vec![[
+        "KEY".to_owned(),
+        "A".to_owned(),
+        "B".to_owned(),
+        "DELTA".to_owned(),
+        "CHANGE".to_owned(),
+    ]];
+    for key in keys {
+        let ctx = options.format_context(&key);
+        let value_a = a.stats.aggregated.get(&key);
+        let value_b = b.stats.aggregated.get(&key);
+        let format = |v: Option<&AggregatedStatsItemValue>| {
+            v.map(|v| v.format_value_sum(ctx))
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| "-".to_owned())
+        };
+        let sum_a = value_a.and_then(|v| v.value_sum);
+        let sum_b = value_b.and_then(|v| v.value_sum);
+        let (delta, change) = match (sum_a, sum_b) {
+            (Some(x), Some(y)) => {
+                let sign = if y > x { "+" } else { "" };
+                let is_integer = [value_a, value_b]
+                    .iter()
+                    .all(|v| v.is_some_and(|v| v.integer_value_sum.is_some()));
+                let precision = if is_integer { 0 } else { ctx.delta_precision };
+                let delta = format!("{sign}{}", ctx.format_number(y - x, precision));
+                let change = if x != 0.0 {
+                    format!("{sign}{:.1}%", (y - x) / x.abs() * 100.0)
+                } else {
+                    "-".to_owned()
+                };
+                (delta, change)
+            }
+            _ => ("-".to_owned(), "-".to_owned()),
+        };
+        rows.push([key, format(value_a), format(value_b), delta, change]);
+    }
+
+    let mut widths = [0; 5];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in &rows {
+        let [key, cells @ ..] = row;
+        print!("{key:<width$}", width = widths[0]);
+        for (cell, width) in cells.iter().zip(&widths[1..]) {
+            print!("  {cell:>width$}");
+        }
+        println!();
+    }
+    Ok(())
+}
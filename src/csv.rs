@@ -0,0 +1,111 @@
+use crate::poll::StatsPoller;
+use crate::stats::{Stats, StatsItemKey};
+use crate::Options;
+use orfail::OrFail;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufWriter, Write as _};
+use std::time::SystemTime;
+
+/// Writer that outputs the aggregated stats of each snapshot as a CSV row (see `--csv-out`).
+///
+/// As the header row consists of all the keys seen in the session, the rows are buffered until `finish()` is called.
+#[derive(Debug)]
+pub struct CsvWriter {
+    file: File,
+    keys: BTreeSet<StatsItemKey>,
+    rows: Vec<CsvRow>,
+}
+
+#[derive(Debug)]
+struct CsvRow {
+    time: SystemTime,
+    connection_count: usize,
+    values: BTreeMap<StatsItemKey, (String, Option<f64>)>,
+}
+
+impl CsvWriter {
+    pub fn new(file: File) -> Self {
+        Self {
+            file,
+            keys: BTreeSet::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn add_stats(&mut self, stats: &Stats) {
+        let values = stats
+            .iter_aggregated()
+            .filter(|(_, item)| item.value_sum.is_some())
+            .map(|(k, item)| {
+                let sum = if let Some(v) = item.integer_value_sum {
+                    v.to_string()
+                } else {
+                    item.value_sum.map(|v| v.to_string()).unwrap_or_default()
+                };
+                (k.clone(), (sum, item.delta_per_sec))
+            })
+            .collect::<BTreeMap<_, _>>();
+        self.keys.extend(values.keys().cloned());
+        self.rows.push(CsvRow {
+            time: stats.time,
+            connection_count: stats.connection_count(),
+            values,
+        });
+    }
+
+    /// Writes the header and the buffered rows to the file.
+    pub fn finish(self) -> orfail::Result<usize> {
+        let mut writer = BufWriter::new(self.file);
+        write!(writer, "time,elapsed,connections").or_fail()?;
+        for key in &self.keys {
+            write!(writer, ",{},{}", escape(key), escape(&format!("{key}/s"))).or_fail()?;
+        }
+        writeln!(writer).or_fail()?;
+
+        let start = self.rows.first().map(|row| row.time);
+        for row in &self.rows {
+            let time = chrono::DateTime::<chrono::Utc>::from(row.time)
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+            let elapsed = start
+                .and_then(|start| row.time.duration_since(start).ok())
+                .unwrap_or_default()
+                .as_secs_f64();
+            write!(writer, "{time},{elapsed},{}", row.connection_count).or_fail()?;
+            for key in &self.keys {
+                let (sum, delta) = row
+                    .values
+                    .get(key)
+                    .map(|(sum, delta)| (sum.as_str(), delta.map(|v| v.to_string())))
+                    .unwrap_or(("", None));
+                write!(writer, ",{sum},{}", delta.unwrap_or_default()).or_fail()?;
+            }
+            writeln!(writer).or_fail()?;
+        }
+        writer.flush().or_fail()?;
+        Ok(self.rows.len())
+    }
+}
+
+/// Converts a whole record file into a CSV file without starting the TUI.
+pub fn convert_record_file(options: Options) -> orfail::Result<()> {
+    let path = options.csv_out.clone().or_fail()?;
+    let rx = StatsPoller::start_thread(options).or_fail()?;
+    let mut count = 0;
+
+    // The polling thread writes the CSV file before disconnecting the channel.
+    while let Ok(stats) = rx.recv() {
+        stats.map_err(orfail::Failure::new)?;
+        count += 1;
+    }
+    println!("Wrote {count} snapshots to {}", path.display());
+    Ok(())
+}
+
+fn escape(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
@@ -0,0 +1,79 @@
+use crate::poll::StatsPoller;
+use crate::stats::{FormatContext, Unit};
+use crate::Options;
+use orfail::OrFail;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+// How often to check whether SIGINT has been received while waiting for the next snapshot.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Records the stats without starting the TUI until SIGINT is received (see `--record-only`).
+pub fn run(options: Options) -> orfail::Result<()> {
+    options
+        .is_realtime_mode()
+        .or_fail_with(|()| "--record-only is not available in replay mode".to_owned())?;
+    let path = options.record.clone().or_fail()?;
+
+    let (stop_tx, stop_rx) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = stop_tx.send(());
+    })
+    .or_fail()?;
+
+    let rx = StatsPoller::start_thread(options).or_fail()?;
+    println!("Recording to {} (press Ctrl-C to stop)", path.display());
+
+    let mut snapshots = 0;
+    let mut errors = 0;
+    let mut connections = 0;
+    let mut last_progress_time: Option<Instant> = None;
+    while stop_rx.try_recv().is_err() {
+        match rx.recv_timeout(STOP_CHECK_INTERVAL) {
+            Ok(Ok(stats)) => {
+                snapshots += 1;
+                connections = stats.connection_count();
+            }
+            Ok(Err(e)) => {
+                log::warn!("failed to poll Sora stats: {e}");
+                errors += 1;
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(orfail::Failure::new(
+                    "the polling thread stopped unexpectedly (see the log for details)",
+                ));
+            }
+        }
+        if snapshots > 0 && last_progress_time.is_none_or(|t| t.elapsed() >= PROGRESS_INTERVAL) {
+            println!(
+                "[{}] snapshots: {snapshots}, connections: {connections}, errors: {errors}, file size: {}",
+                chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                format_file_size(&path)
+            );
+            last_progress_time = Some(Instant::now());
+        }
+    }
+
+    // Dropping the receiver makes the polling thread write out the pending records.
+    drop(rx);
+    println!(
+        "Recorded {snapshots} snapshots to {} ({})",
+        path.display(),
+        format_file_size(&path)
+    );
+    Ok(())
+}
+
+fn format_file_size(path: &Path) -> String {
+    let ctx = FormatContext {
+        unit: Some(Unit::Bytes),
+        ..Default::default()
+    };
+    std::fs::metadata(path)
+        .map(|m| ctx.format_number(m.len() as f64, 0))
+        .unwrap_or_else(|_| "unknown".to_owned())
+}
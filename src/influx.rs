@@ -0,0 +1,109 @@
+use crate::stats::{Stats, StatsItemValue};
+use orfail::OrFail;
+use std::fs::File;
+use std::io::{BufWriter, Write as _};
+use std::time::UNIX_EPOCH;
+
+const AGGREGATED_MEASUREMENT: &str = "sorastats_aggregated";
+const CONNECTION_MEASUREMENT: &str = "sorastats_connection";
+
+/// Writer that outputs the numeric stats values in the InfluxDB line protocol.
+#[derive(Debug)]
+pub struct InfluxWriter {
+    writer: BufWriter<File>,
+}
+
+impl InfluxWriter {
+    pub fn new(file: File) -> Self {
+        Self {
+            writer: BufWriter::new(file),
+        }
+    }
+
+    pub fn write_stats(&mut self, stats: &Stats) -> orfail::Result<()> {
+        let timestamp = stats.time.duration_since(UNIX_EPOCH).or_fail()?.as_nanos();
+
+        let fields = stats
+            .iter_aggregated()
+            .filter_map(|(k, item)| {
+                let value = if let Some(v) = item.integer_value_sum {
+                    format_integer(v)
+                } else {
+                    item.value_sum?.to_string()
+                };
+                Some((k, value))
+            })
+            .collect::<Vec<_>>();
+        self.write_line(AGGREGATED_MEASUREMENT, None, &fields, timestamp)
+            .or_fail()?;
+
+        for conn in stats.iter_connections() {
+            let fields = conn
+                .iter_items()
+                .filter_map(|(k, item)| {
+                    let value = match item.value {
+                        StatsItemValue::Number(v) => v.to_string(),
+                        StatsItemValue::UInt(v) => format_integer(i128::from(v)),
+                        StatsItemValue::Int(v) => format_integer(i128::from(v)),
+                        _ => return None,
+                    };
+                    Some((k, value))
+                })
+                .collect::<Vec<_>>();
+            self.write_line(
+                CONNECTION_MEASUREMENT,
+                Some(&conn.connection_id),
+                &fields,
+                timestamp,
+            )
+            .or_fail()?;
+        }
+        self.writer.flush().or_fail()?;
+        Ok(())
+    }
+
+    fn write_line(
+        &mut self,
+        measurement: &str,
+        connection_id: Option<&str>,
+        fields: &[(&String, String)],
+        timestamp: u128,
+    ) -> orfail::Result<()> {
+        if fields.is_empty() {
+            // A line without fields is invalid.
+            return Ok(());
+        }
+
+        write!(self.writer, "{measurement}").or_fail()?;
+        if let Some(id) = connection_id {
+            write!(self.writer, ",connection_id={}", escape(id)).or_fail()?;
+        }
+        for (i, (k, v)) in fields.iter().enumerate() {
+            let delimiter = if i == 0 { ' ' } else { ',' };
+            write!(self.writer, "{delimiter}{}={v}", escape(k)).or_fail()?;
+        }
+        writeln!(self.writer, " {timestamp}").or_fail()?;
+        Ok(())
+    }
+}
+
+fn format_integer(v: i128) -> String {
+    if let Ok(v) = i64::try_from(v) {
+        format!("{v}i")
+    } else {
+        // Out of the range of InfluxDB integers.
+        (v as f64).to_string()
+    }
+}
+
+/// Escapes a tag key, tag value or field key.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, ',' | '=' | ' ' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -1,25 +1,154 @@
 use orfail::OrFail;
 use std::fs::File;
-use std::io::BufWriter;
 use std::path::PathBuf;
 
+pub mod alert;
+pub mod anonymize;
+pub mod compare;
+pub mod csv;
+pub mod daemon;
+pub mod influx;
 pub mod poll;
+pub mod record;
 pub mod stats;
+pub mod theme;
 pub mod ui;
 
+/// Upper bound of `--delta-precision` (keeps the fixed-point formatting within `i128`).
+pub const MAX_DELTA_PRECISION: u64 = 9;
+
 #[derive(Debug, Clone, clap::Parser)]
 pub struct Options {
     /// 「Sora の API の URL（リアルタイムモード）」あるいは「過去に `--record` で記録したファイルのパス（リプレイモード）」
+    #[clap(
+        required_unless_present = "compare",
+        default_value = "",
+        hide_default_value = true
+    )]
     pub sora_api_url: String,
 
+    /// `--record` で記録した二つのファイルの最終時点の統計値を比較して、キー毎の差分を表として出力し終了する
+    ///
+    /// TUI は起動せずに、 Aggregated Stats の各キーについて「A の値」「B の値」「差分」「変化率」を標準出力に表示する。
+    /// `--replay-to` に相対時刻（例: "+60s"）を指定した場合には、それぞれのファイルの先頭から同じ経過時間の時点が比較される。
+    /// `--stats-key-filter` と `--connection-filter` も適用される
+    #[clap(long, num_args = 2, value_names = ["FILE_A", "FILE_B"])]
+    pub compare: Option<Vec<PathBuf>>,
+
+    /// 統計 API の呼び出し時に "x-sora-target" ヘッダーに指定する値
+    ///
+    /// この値は `--record` で記録したファイルのメタデータにも保存される
+    #[clap(long, default_value = poll::DEFAULT_SORA_TARGET)]
+    pub sora_target: String,
+
+    /// 統計 API の呼び出しに使う HTTP メソッド
+    ///
+    /// Sora の統計 API は POST で呼び出す必要があるが、
+    /// POST を許可しないリバースプロキシ（メソッドを変換して Sora に転送するもの）を経由する場合には "get" を指定する
+    #[clap(long, value_enum, default_value_t = HttpMethod::Post)]
+    pub http_method: HttpMethod,
+
+    /// 指定のコネクションの統計情報のみを取得・表示する
+    ///
+    /// 統計 API の呼び出し時に {"connection_id": ID} をリクエストボディとして送信する。
+    /// 単一コネクション用の API を使う場合には "--sora-target Sora_20171101.GetStatsConnection" のように `--sora-target` も合わせて指定すること。
+    /// レスポンスには JSON オブジェクトと JSON 配列のどちらも指定可能で、配列の場合は該当するコネクション以外は無視される。
+    /// このオプションが指定された場合には、起動時に個々のコネクションのテーブルにフォーカスが当たる
+    #[clap(long, value_name = "ID", conflicts_with = "stream")]
+    pub connection_id: Option<String>,
+
+    /// リアルタイムモードで、起動から指定の時間が経過したら自動的に終了する（例: "30s"）
+    ///
+    /// CI でのスモークテストなど、一定時間だけ統計情報の取得を確認したい場合に有用
+    #[clap(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub run_for: Option<std::time::Duration>,
+
+    /// リアルタイムモードで、指定の回数だけ統計情報を取得したら自動的に終了する（取得に失敗した回も含む）
+    #[clap(long, value_name = "N")]
+    pub max_polls: Option<std::num::NonZeroUsize>,
+
+    /// 統計 API の呼び出し時に追加で指定する HTTP ヘッダー（複数指定可能）
+    ///
+    /// 例えば "--header 'Authorization: Bearer ${SORA_TOKEN}'" のように指定する。
+    /// なお、このオプションと `<SORA_API_URL>` 引数および `--record` の値に含まれる "${VAR}" は、
+    /// 起動時に環境変数の値に置換される（未定義の環境変数が参照された場合はエラーとなる）
+    #[clap(long = "header", value_name = "NAME:VALUE", value_parser = parse_header)]
+    pub headers: Vec<(String, String)>,
+
+    /// TLS のクライアント認証（mTLS）に使用する PEM 形式のクライアント証明書のパス
+    #[clap(long, value_name = "PATH", requires = "client_key")]
+    pub client_cert: Option<PathBuf>,
+
+    /// `--client-cert` の証明書に対応する PEM 形式の秘密鍵のパス
+    #[clap(long, value_name = "PATH", requires = "client_cert")]
+    pub client_key: Option<PathBuf>,
+
+    /// 【危険】 TLS のサーバ証明書の検証を行わない
+    ///
+    /// 自己署名証明書を使用したローカルの開発・検証用の Sora に接続するためのオプションで、
+    /// 通信の盗聴や改ざんを検知できなくなるため、本番環境に対しては絶対に使用しないこと
+    #[clap(long)]
+    pub insecure: bool,
+
+    /// 統計 API への接続のタイムアウト（例: "5s", "0.5"）
+    #[clap(long, value_name = "DURATION", default_value = "5s", value_parser = parse_duration)]
+    pub connect_timeout: std::time::Duration,
+
+    /// 統計 API のレスポンスの読み込みのタイムアウト（例: "10s"）
+    ///
+    /// 応答しないエンドポイントへのリクエストはこの時間で失敗扱いとなり、次のポーリングで再試行される。
+    /// `--stream` 指定時には、イベントの到着間隔が長くなり得るため適用されない
+    #[clap(long, value_name = "DURATION", default_value = "10s", value_parser = parse_duration)]
+    pub read_timeout: std::time::Duration,
+
     /// 統計 API から情報を取得する間隔（秒単位）
-    #[clap(long, short = 'i', default_value = "1")]
+    ///
+    /// 時間を指定するオプションでは "90s", "5m", "2h", "1h30m" のように単位を付けることもできる（単位がない場合は秒として扱われる）
+    #[clap(long, short = 'i', default_value = "1", value_parser = parse_nonzero_secs)]
     pub polling_interval: std::num::NonZeroUsize,
 
     /// チャートの X 軸の表示期間（秒単位）
-    #[clap(long, short = 'p', default_value = "60")]
+    #[clap(long, short = 'p', default_value = "60", value_parser = parse_nonzero_secs)]
     pub chart_time_period: std::num::NonZeroUsize,
 
+    /// 統計 API を定期的にポーリングする代わりに、 Server-Sent Events 形式のストリーミング API から統計情報を受信する
+    ///
+    /// 各イベントの "data" には、 `GetStatsAllConnections` のレスポンスと同じ形式の JSON 配列が格納されていることを想定している。
+    /// 接続が切れた場合には `--polling-interval` の間隔で再接続が試みられる
+    #[clap(long)]
+    pub stream: bool,
+
+    /// チャートを罫線や点字文字を使わずに ASCII 文字（'-', '|', '*'）のみで描画する
+    ///
+    /// 環境変数 TERM が未設定もしくは "dumb" の場合には、このオプションの指定がなくても ASCII 描画が使用される
+    #[clap(long)]
+    pub ascii_chart: bool,
+
+    /// チャートの描画方法（"line", "scatter", "area" のいずれか）
+    ///
+    /// "area" を指定すると、折れ線の下の領域が塗りつぶされる（比較対象のキーの系列は常に折れ線で描画される）
+    #[clap(long, value_enum, default_value_t = ChartType::Line)]
+    pub chart_type: ChartType,
+
+    /// UI の配色（"dark", "light", "high-contrast" のいずれか）
+    ///
+    /// 明るい背景色の端末では "light" を、視認性を優先する場合には "high-contrast" を指定する
+    #[clap(long, value_enum, default_value_t = theme::ThemeName::Dark)]
+    pub theme: theme::ThemeName,
+
+    /// チャートに重ねて表示する移動平均（'m' キーで表示を切り替え）の窓幅（秒単位）
+    ///
+    /// 省略された場合には `--chart-time-period` の 1/10 の値が使用される
+    #[clap(long, value_parser = parse_nonzero_secs)]
+    pub moving_average_window: Option<std::num::NonZeroUsize>,
+
+    /// 差分値（Delta/s）の計算に使用するサンプル数
+    ///
+    /// 1 より大きい値を指定すると、直前のサンプルとの差分ではなく、この数だけ前のサンプルからの変化量を
+    /// 実際の経過時間で割った値が表示されるため、ポーリング間隔のゆらぎによるチャートのがたつきが抑えられる
+    #[clap(long, default_value = "1")]
+    pub delta_window: std::num::NonZeroUsize,
+
     /// 集計対象に含めるコネクションをフィルタするための正規表現
     ///
     /// コネクションの各統計値は "${KEY}:${VALUE}" という形式の文字列に変換された上で、
@@ -31,15 +160,133 @@ pub struct Options {
     #[clap(long, short = 'c', default_value = ".*:.*")]
     pub connection_filter: regex::Regex,
 
-    /// 集計対象に含める統計項目をフィルタするための正規表現
+    /// 統計値に対するアラートの条件（複数指定可能）
+    ///
+    /// "KEY>THRESHOLD" 形式（演算子は ">", ">=", "<", "<=" のいずれか）で指定し、
+    /// いずれかのコネクションの値が条件を満たす場合にはヘッダーにアラートが表示される。
+    /// キーの末尾に "/s" をつけた場合（例: "rtp.packets_lost/s>10"）には、値の代わりに一秒当たりの差分値が判定に使われる
+    #[clap(long = "alert", value_name = "KEY>THRESHOLD")]
+    pub alerts: Vec<alert::AlertRule>,
+
+    /// リアルタイムモードで、新たにアラートの条件が満たされた時点で表示を一時停止する
+    ///
+    /// 一時停止中も統計情報の取得と `--record` による記録は継続される（'p' キーで再開）
+    #[clap(long)]
+    pub pause_on_alert: bool,
+
+    /// リアルタイムモードで、新たに条件を満たしたアラートを保持する件数（'a' キーでアラートログとして表示）
+    ///
+    /// この件数を超えた場合には、古いものから破棄される
+    #[clap(long, default_value = "100")]
+    pub alert_log_size: std::num::NonZeroUsize,
+
+    /// 'b' キーで表示されるヒストグラム（選択中の統計値のコネクション間での分布）の階級数（1 から 100 まで）
+    #[clap(
+        long,
+        default_value = "10",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=100)
+    )]
+    pub histogram_buckets: usize,
+
+    /// 表示するコネクション数の上限
+    ///
+    /// `--connection-filter` の適用後のコネクション数がこの値を超える場合には、
+    /// コネクション ID 順で先頭のものだけが集計・表示の対象となる。
+    /// 非常に多くのコネクションが存在する環境で、表示を軽くしたい場合に有用
+    #[clap(long)]
+    pub max_connections: Option<std::num::NonZeroUsize>,
+
+    /// 表示する統計項目をフィルタするための正規表現
     ///
     /// 指定された正規表現にマッチ（部分一致）する統計項目のみが表示される。
+    /// フィルタは描画時に適用されるので、起動後に '/' キーで変更することもできる
+    /// （`--record` で記録されるファイルには、このフィルタに関わらず全ての統計項目が保存される）。
     ///
     /// 例えば、 RTP 関連の統計情報のみを対象としたい場合には
     /// "^rtp[.]" という正規表現を指定すると良い。
     #[clap(long, short = 'k', default_value = ".*")]
     pub stats_key_filter: regex::Regex,
 
+    /// 名前付きの `--stats-key-filter` のプリセット（複数指定可能）
+    ///
+    /// 起動後に Tab キーで、アクティブなフィルタをプリセット間で順番に切り替えることができる。
+    /// 例えば "--filter-preset bandwidth=byte --filter-preset loss=packets_lost" のように指定する
+    #[clap(long = "filter-preset", value_name = "NAME=REGEX", value_parser = parse_filter_preset)]
+    pub filter_presets: Vec<(String, regex::Regex)>,
+
+    /// 指定されたキーの統計値でコネクションをグループ分けして、グループ毎の集計値を表示する
+    ///
+    /// 例えば "channel_id" や "role" を指定すると、チャンネル毎やロール毎の合計値を確認することができる。
+    /// グループのテーブルで行を選択すると、個々のコネクションの表示がそのグループに属するものに絞り込まれる
+    #[clap(long, value_name = "KEY")]
+    pub group_by: Option<String>,
+
+    /// Aggregated Stats テーブルのキーをメディア種別（音声・映像など）のセクション毎にまとめて表示する（'S' キーで切り替え）
+    #[clap(long)]
+    pub split_by_media: bool,
+
+    /// `--split-by-media` で使用するセクション名とキーのパターン（"NAME=REGEX" 形式、複数指定可能）
+    ///
+    /// 指定がない場合には "audio=(?i)audio" と "video=(?i)video" が使用される。
+    /// キーは最初にマッチしたセクションに分類され、どのパターンにもマッチしないキーは "other" セクションに表示される
+    #[clap(long, value_name = "NAME=REGEX", value_parser = parse_filter_preset)]
+    pub media_section: Vec<(String, regex::Regex)>,
+
+    /// 差分値（Delta/s）を表示する際の小数点以下の桁数
+    ///
+    /// デフォルトでは整数に丸めて表示されるので、パケットロス率のような変化の緩やかな統計値を見る場合には、
+    /// 1 以上の値を指定すると良い（最大値は 9）
+    #[clap(
+        long,
+        default_value = "0",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(0..=MAX_DELTA_PRECISION)
+    )]
+    pub delta_precision: usize,
+
+    /// 差分値（Delta/s）を絶対値ではなく、差分の基準となる値に対する変化率（%/s）で表示する（'%' キーで切り替え可能）
+    ///
+    /// 基準となる値が 0 の場合には "∞" （差分も 0 の場合には "-"）と表示される
+    #[clap(long)]
+    pub percent_delta: bool,
+
+    /// 統計値や差分値を表示用の桁数に丸める際の方式
+    ///
+    /// "half-up" の場合には 0 から遠い方に（例: 999.5 は 1,000）、
+    /// "half-even" の場合には偶数の方に（例: 999.5 は 1,000、 998.5 は 998）丸められる
+    #[clap(long, value_enum, default_value_t = stats::RoundingMode::HalfUp)]
+    pub rounding: stats::RoundingMode,
+
+    /// 統計情報の取得時刻の表示形式
+    ///
+    /// "relative" を指定すると、最初の統計情報の取得時刻を起点とした相対時刻（例: "+00:01:23.450"）で表示される。
+    /// 記録ファイルを共有する際に、実際の日時を伏せたい場合などに有用
+    #[clap(long, value_enum, default_value_t = TimeDisplay::Absolute)]
+    pub time_display: TimeDisplay,
+
+    /// 時刻をローカルタイムゾーンではなく UTC で表示する（'U' キーで切り替え可能）
+    #[clap(long)]
+    pub utc: bool,
+
+    /// 単位のない数値を "1.2k", "3.4M", "5.6G" のように短縮して表示する（'k' キーで切り替え可能）
+    ///
+    /// `--unit` で単位が指定された統計キーには、その単位に応じた表示が優先される
+    #[clap(long)]
+    pub compact_numbers: bool,
+
+    /// 画面の左右の列の配置
+    ///
+    /// "details-left" を指定すると、 Aggregated Stats テーブルが右側に、個々のコネクションのテーブルとチャートが左側に表示される（'o' キーで切り替え可能）
+    #[clap(long, value_enum, default_value_t = ColumnLayout::AggregatedLeft)]
+    pub layout: ColumnLayout,
+
+    /// 個々のコネクションのテーブルの初期の並び順（"KEY" あるいは "KEY:asc" / "KEY:desc" 形式）
+    ///
+    /// KEY に "id" を指定するとコネクション ID 順に、それ以外の統計キーを指定するとその値の順に並べられる。
+    /// 値が数値ではないコネクションは末尾に配置され、数値の値を持つコネクションが一つもない場合にはコネクション ID 順となる。
+    /// 's' キーによる自然順ソートの切り替えは、 ID の比較方法（値が同じ場合も含む）に反映される
+    #[clap(long, value_name = "KEY[:asc|desc]")]
+    pub sort_connections_by: Option<ConnectionSort>,
+
     /// 指定されたファイルに、取得した統計情報を記録する
     ///
     ///
@@ -49,20 +296,479 @@ pub struct Options {
     /// リプレイモードの場合には、このオプションを指定しても無視される
     #[clap(long)]
     pub record: Option<PathBuf>,
+
+    /// TUI を起動せずに、 `--record` で指定されたファイルへの記録のみを行う
+    ///
+    /// 長時間の無人での記録向けで、定期的に進捗（コネクション数とファイルサイズ）を標準出力に表示する。
+    /// Ctrl-C (SIGINT) を受け取ると、記録ファイルを書き出してから終了する
+    #[clap(long, requires = "record")]
+    pub record_only: bool,
+
+    /// `--record` で記録するファイルの形式
+    ///
+    /// "compact" を指定すると、統計値のキー（JSON 内のパス）を一度だけ辞書として記録し、
+    /// 以降のスナップショットでは値のみを記録するため、長時間の記録のファイルサイズを大幅に削減できる。
+    /// リプレイ時にはどちらの形式も自動的に判別される
+    #[clap(long, value_enum, default_value_t = record::RecordFileFormat::Jsonl)]
+    pub record_format: record::RecordFileFormat,
+
+    /// `--record` で記録するファイルのメタデータに保存するラベル（例: "load test run 3"）
+    ///
+    /// リプレイ時には Status ブロックのタイトルに表示される
+    #[clap(long, value_name = "TEXT")]
+    pub label: Option<String>,
+
+    /// `--record` で記録するファイルのメタデータに保存する自由記述のメモ
+    ///
+    /// リプレイ時には Status ブロックに表示される
+    #[clap(long, value_name = "TEXT")]
+    pub note: Option<String>,
+
+    /// 指定されたファイルに、取得した統計情報の数値を InfluxDB の line protocol 形式で出力する
+    ///
+    /// 集計値は "sorastats_aggregated" 、個々のコネクションの値は "sorastats_connection" という measurement で出力され、
+    /// 後者には "connection_id" タグが付与される。
+    /// リプレイモードで指定した場合には、記録ファイルの内容を変換して出力することができる
+    #[clap(long, value_name = "PATH")]
+    pub influx_out: Option<PathBuf>,
+
+    /// 指定されたファイルに、スナップショット毎の集計値（各キーの合計値と Delta/s）を CSV 形式で出力する
+    ///
+    /// ヘッダ行にはセッション中に出現した全てのキーが含まれるため、ファイルへの書き込みは終了時にまとめて行われる。
+    /// リプレイモードで指定した場合には、 TUI を起動せずに記録ファイル全体を CSV に変換して終了する
+    #[clap(long, value_name = "PATH")]
+    pub csv_out: Option<PathBuf>,
+
+    /// `--record` で記録中のファイルへの書き込みをフラッシュする最小間隔（秒単位）
+    ///
+    /// デフォルトでは統計情報を取得する度にフラッシュされる。
+    /// 大きな値を指定すると I/O の負荷が下がる代わりに、
+    /// クラッシュ時には最大でこの期間分の記録が失われる可能性がある
+    #[clap(long, default_value = "0", value_parser = parse_secs)]
+    pub record_flush_interval: u64,
+
+    /// コネクション ID を "conn-0001" のような仮名に置き換える
+    ///
+    /// 置換は統計情報の取得直後に行われるため、表示だけでなく `--record` や `--influx-out` などの出力にも適用される。
+    /// 同じコネクション ID は常に同じ仮名（出現順の連番）に置き換えられるので、差分値や集計結果には影響しない
+    #[clap(long)]
+    pub anonymize: bool,
+
+    /// `--anonymize` 指定時に、コネクション ID に加えて仮名に置き換える文字列値の統計キー（複数指定可能）
+    ///
+    /// 例えば "--anonymize-key client_id" を指定すると "client_id" の値は "client_id-0001" のように置き換えられる
+    #[clap(long = "anonymize-key", value_name = "KEY", requires = "anonymize")]
+    pub anonymize_keys: Vec<String>,
+
+    /// リプレイモードで記録ファイルの末尾に到達した後も、追記される統計情報を待ち続ける（`tail -f` と同様）
+    ///
+    /// 別のプロセスが `--record` で記録中のファイルを指定することで、その内容をリアルタイムに閲覧することができる
+    ///
+    /// リアルタイムモードの場合には、このオプションを指定しても無視される。
+    /// また、名前付きパイプ（FIFO）などの通常ファイル以外が指定された場合には、このオプションの指定がなくても有効になる
+    #[clap(long)]
+    pub follow: bool,
+
+    /// リプレイモードで、指定の時刻より前の統計情報を読み飛ばす
+    ///
+    /// RFC3339 形式の時刻（例: "2024-01-01T12:00:00+09:00"）、
+    /// あるいは記録ファイルの先頭からの相対時間（例: "+90", "+5m"）を指定可能
+    #[clap(long, value_name = "TIME", value_parser = parse_replay_time)]
+    pub replay_from: Option<ReplayTime>,
+
+    /// リプレイモードで、指定の時刻より後の統計情報を読み込まない（形式は `--replay-from` と同様）
+    #[clap(long, value_name = "TIME", value_parser = parse_replay_time)]
+    pub replay_to: Option<ReplayTime>,
+
+    /// リプレイモードで、 N 件ごとに 1 件の統計情報のみを読み込む（間引き）
+    ///
+    /// 差分値は間引き後の統計情報の間で計算される。
+    /// 高頻度で記録されたファイルを再生する場合に有用
+    #[clap(long, value_name = "N", default_value = "1")]
+    pub replay_stride: std::num::NonZeroUsize,
+
+    /// 代替スクリーンを使わずに、通常のスクリーンに描画する
+    ///
+    /// 終了後も最後に描画された内容がターミナルのスクロールバックに残るので、
+    /// CI のログなどに最終状態を残したい場合に有用
+    #[clap(long)]
+    pub no_altscreen: bool,
+
+    /// マウス操作を有効にする
+    ///
+    /// テーブルの行をクリックして選択したり、リプレイモードで表示されるシークバーをクリックして表示位置を移動したりできる。
+    /// ターミナルでのテキスト選択が効かなくなるため、デフォルトでは無効になっている
+    #[clap(long)]
+    pub mouse: bool,
+
+    /// ヘッダーを一行のステータスバーにまとめて、テーブルとチャートのみを表示するコンパクトなレイアウトを使う
+    ///
+    /// tmux の小さなペインなどに埋め込む場合に有用。
+    /// なお、ターミナルの高さが小さい場合には、このオプションの指定の有無に関わらずコンパクトなレイアウトが使われる
+    #[clap(long)]
+    pub compact: bool,
+
+    /// 数値以外の統計値（例: "connection_state"）について、値の遷移を追跡して表示する
+    ///
+    /// 指定された場合には、個々のコネクションの統計値の表示に、
+    /// 値が変化した回数と、最後に変化してからの経過時間が追加される
+    #[clap(long)]
+    pub track_transitions: bool,
+
+    /// 集計テーブルの先頭に常に表示する統計項目のキー（複数指定可能）
+    ///
+    /// 起動後に 'P' キーで、選択中の統計項目のピン留めを切り替えることもできる。
+    /// なお、ピン留めされた統計項目であっても `--stats-key-filter` にマッチしない場合には表示されない
+    #[clap(long = "pin", value_name = "KEY")]
+    pub pinned_keys: Vec<String>,
+
+    /// コネクション ID の表示形式
+    ///
+    /// "head" と "tail" はそれぞれ ID の先頭と末尾の `--connection-id-length` 文字のみを表示し、
+    /// "hash" は ID から導出した短いハッシュ値を表示する
+    #[clap(long, value_enum, default_value_t = ConnectionIdFormat::Full)]
+    pub connection_id_format: ConnectionIdFormat,
+
+    /// `--connection-id-format` に "head" あるいは "tail" が指定された場合に表示する文字数
+    #[clap(long, default_value = "8")]
+    pub connection_id_length: std::num::NonZeroUsize,
+
+    /// コネクション ID の代わりに表示する別名（複数指定可能）
+    ///
+    /// 別名が指定されたコネクションについては `--connection-id-format` の指定は無視される
+    #[clap(long = "alias", value_name = "CONNECTION_ID=NAME", value_parser = parse_key_value)]
+    pub connection_aliases: Vec<(String, String)>,
+
+    /// Aggregated Stats テーブルの統計キーの表示を短縮するためのプレフィックスの別名（複数指定可能）
+    ///
+    /// 例えば "--key-alias rtp.inbound.audio.=ia." を指定すると "rtp.inbound.audio.packets_received" は "ia.packets_received" と表示される。
+    /// 複数の別名に該当する場合には、最も長いプレフィックスのものが使用される。
+    /// 表示上の変更のみで `--stats-key-filter` などは元のキーに対して適用される
+    #[clap(long = "key-alias", value_name = "PREFIX=SHORT", value_parser = parse_key_value)]
+    pub key_aliases: Vec<(String, String)>,
+
+    /// 統計キーの値の単位（複数指定可能）
+    ///
+    /// 正規表現 PATTERN にマッチ（部分一致）する統計キーの値（および差分値）を、単位に応じた形式で表示する。
+    /// 例えば "--unit 'jitter$=seconds'" を指定すると 0.013 は "13 ms" と、 "--unit 'bytes_=bytes'" では "1.5 MiB" のように表示される。
+    /// 複数のパターンにマッチする場合には、最初に指定されたものが使用される（どれにもマッチしない場合は通常の整数表示となる）
+    #[clap(long = "unit", value_name = "PATTERN=UNIT", value_parser = parse_unit)]
+    pub units: Vec<(regex::Regex, stats::Unit)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConnectionIdFormat {
+    Full,
+    Head,
+    Tail,
+    Hash,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum HttpMethod {
+    Get,
+    Post,
+}
+
+impl HttpMethod {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Get => "GET",
+            Self::Post => "POST",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionSort {
+    /// Stats key to sort by (`None` means the connection ID).
+    pub key: Option<stats::StatsItemKey>,
+    pub descending: bool,
+}
+
+impl std::str::FromStr for ConnectionSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, descending) = match s.rsplit_once(':') {
+            Some((key, "asc")) => (key, false),
+            Some((key, "desc")) => (key, true),
+            _ => (s, false),
+        };
+        if key.is_empty() {
+            return Err("empty sort key".to_owned());
+        }
+        Ok(Self {
+            key: (key != "id").then(|| key.to_owned()),
+            descending,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColumnLayout {
+    AggregatedLeft,
+    DetailsLeft,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ChartType {
+    Line,
+    Scatter,
+    Area,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TimeDisplay {
+    Absolute,
+    Relative,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayTime {
+    Absolute(std::time::SystemTime),
+
+    /// Offset from the time of the first record.
+    Offset(std::time::Duration),
+}
+
+impl ReplayTime {
+    pub fn resolve(self, origin: std::time::SystemTime) -> std::time::SystemTime {
+        match self {
+            Self::Absolute(time) => time,
+            Self::Offset(offset) => origin + offset,
+        }
+    }
+}
+
+/// FNV-1a hash (stable across runs and platforms).
+fn fnv1a_hash(s: &str) -> u32 {
+    s.bytes().fold(0x811c9dc5u32, |h, b| {
+        (h ^ u32::from(b)).wrapping_mul(0x01000193)
+    })
+}
+
+fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    let (k, v) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected `KEY=VALUE` format, but got {s:?}"))?;
+    Ok((k.to_owned(), v.to_owned()))
+}
+
+fn parse_unit(s: &str) -> Result<(regex::Regex, stats::Unit), String> {
+    let (pattern, unit) = parse_key_value(s)?;
+    let regex = regex::Regex::new(&pattern).map_err(|e| e.to_string())?;
+    let unit = <stats::Unit as clap::ValueEnum>::from_str(&unit, true)?;
+    Ok((regex, unit))
+}
+
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected `NAME:VALUE` format, but got {s:?}"))?;
+    Ok((name.trim().to_owned(), value.trim().to_owned()))
+}
+
+/// Replaces `${VAR}` in the given string with the value of the environment variable.
+fn expand_env_vars(s: &str) -> orfail::Result<String> {
+    let mut expanded = String::new();
+    let mut rest = s;
+    while let Some(i) = rest.find("${") {
+        expanded.push_str(&rest[..i]);
+        let (name, remaining) = rest[i + 2..]
+            .split_once('}')
+            .or_fail_with(|_| format!("unterminated `${{` in {s:?}"))?;
+        let value = std::env::var(name)
+            .or_fail_with(|e| format!("failed to expand `${{{name}}}` in {s:?}: {e}"))?;
+        expanded.push_str(&value);
+        rest = remaining;
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Parses a duration such as "90", "90s", "5m", "2h" or "1h30m" (bare numbers are seconds).
+fn parse_duration(s: &str) -> Result<std::time::Duration, String> {
+    if let Ok(secs) = s.parse::<f64>() {
+        return std::time::Duration::try_from_secs_f64(secs)
+            .map_err(|e| format!("invalid duration {s:?}: {e}"));
+    }
+
+    let mut total = 0.0;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let n = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let (number, unit_and_rest) = rest.split_at(n);
+        let m = unit_and_rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(unit_and_rest.len());
+        let (unit, next) = unit_and_rest.split_at(m);
+        let number: f64 = number
+            .parse()
+            .map_err(|_| format!("invalid duration {s:?}"))?;
+        let scale = match unit {
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 60.0 * 60.0,
+            _ => {
+                return Err(format!(
+                    "invalid duration {s:?}: unknown unit {unit:?} (expected \"s\", \"m\" or \"h\")"
+                ))
+            }
+        };
+        total += number * scale;
+        rest = next;
+    }
+    std::time::Duration::try_from_secs_f64(total)
+        .map_err(|e| format!("invalid duration {s:?}: {e}"))
+}
+
+fn parse_secs(s: &str) -> Result<u64, String> {
+    let duration = parse_duration(s)?;
+    if duration.subsec_nanos() != 0 {
+        return Err(format!("{s:?} is not a whole number of seconds"));
+    }
+    Ok(duration.as_secs())
+}
+
+fn parse_nonzero_secs(s: &str) -> Result<std::num::NonZeroUsize, String> {
+    let secs = parse_secs(s)?;
+    usize::try_from(secs)
+        .ok()
+        .and_then(std::num::NonZeroUsize::new)
+        .ok_or_else(|| format!("{s:?} must be at least one second"))
+}
+
+fn parse_replay_time(s: &str) -> Result<ReplayTime, String> {
+    if let Some(offset) = s.strip_prefix('+') {
+        return parse_duration(offset).map(ReplayTime::Offset);
+    }
+    let time = chrono::DateTime::parse_from_rfc3339(s).map_err(|e| {
+        format!("expected RFC3339 time or relative offset like \"+5m\", but got {s:?}: {e}")
+    })?;
+    Ok(ReplayTime::Absolute(time.into()))
+}
+
+fn parse_filter_preset(s: &str) -> Result<(String, regex::Regex), String> {
+    let (name, pattern) = parse_key_value(s)?;
+    let regex = regex::Regex::new(&pattern).map_err(|e| e.to_string())?;
+    Ok((name, regex))
 }
 
 impl Options {
-    fn create_recorder(&self) -> orfail::Result<Option<BufWriter<File>>> {
+    /// Expands the environment variables referred in the URL, headers and record path.
+    pub fn expand_env_vars(&mut self) -> orfail::Result<()> {
+        self.sora_api_url = expand_env_vars(&self.sora_api_url).or_fail()?;
+        for (_, value) in &mut self.headers {
+            *value = expand_env_vars(value).or_fail()?;
+        }
+        if let Some(path) = self.record.as_ref().and_then(|path| path.to_str()) {
+            self.record = Some(expand_env_vars(path).or_fail()?.into());
+        }
+        Ok(())
+    }
+
+    fn create_recorder(&self) -> orfail::Result<Option<record::Recorder>> {
         if let Some(path) = &self.record {
             let file = File::create(path)
                 .or_fail_with(|e| format!("failed to create record file {path:?}: {e}"))?;
-            Ok(Some(BufWriter::new(file)))
+            let metadata = record::RecordMetadata::new(self);
+            let flush_interval = std::time::Duration::from_secs(self.record_flush_interval);
+            Ok(Some(record::Recorder::new(file, metadata, flush_interval)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn create_csv_writer(&self) -> orfail::Result<Option<csv::CsvWriter>> {
+        if let Some(path) = &self.csv_out {
+            let file = File::create(path)
+                .or_fail_with(|e| format!("failed to create output file {path:?}: {e}"))?;
+            Ok(Some(csv::CsvWriter::new(file)))
         } else {
             Ok(None)
         }
     }
 
-    fn is_realtime_mode(&self) -> bool {
+    fn create_influx_writer(&self) -> orfail::Result<Option<influx::InfluxWriter>> {
+        if let Some(path) = &self.influx_out {
+            let file = File::create(path)
+                .or_fail_with(|e| format!("failed to create output file {path:?}: {e}"))?;
+            Ok(Some(influx::InfluxWriter::new(file)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn format_context(&self, key: &str) -> stats::FormatContext {
+        stats::FormatContext {
+            delta_precision: self.delta_precision,
+            rounding: self.rounding,
+            unit: self
+                .units
+                .iter()
+                .find(|(pattern, _)| pattern.is_match(key))
+                .map(|(_, unit)| *unit),
+            compact_numbers: self.compact_numbers,
+            percent_delta: self.percent_delta,
+        }
+    }
+
+    /// Enables `--ascii-chart` if `TERM` indicates a terminal without line-drawing support.
+    pub fn detect_ascii_chart(&mut self) {
+        self.ascii_chart |= std::env::var("TERM")
+            .ok()
+            .filter(|term| !term.is_empty() && term != "dumb")
+            .is_none();
+    }
+
+    fn moving_average_window(&self) -> f64 {
+        if let Some(window) = self.moving_average_window {
+            window.get() as f64
+        } else {
+            self.chart_time_period.get() as f64 / 10.0
+        }
+    }
+
+    fn display_stats_key(&self, key: &str) -> String {
+        self.key_aliases
+            .iter()
+            .filter(|(prefix, _)| key.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(prefix, short)| format!("{short}{}", &key[prefix.len()..]))
+            .unwrap_or_else(|| key.to_owned())
+    }
+
+    fn display_connection_id(&self, id: &str) -> String {
+        if let Some((_, name)) = self.connection_aliases.iter().find(|(k, _)| k == id) {
+            return name.clone();
+        }
+
+        let n = self.connection_id_length.get();
+        match self.connection_id_format {
+            ConnectionIdFormat::Full => id.to_owned(),
+            ConnectionIdFormat::Head => id.chars().take(n).collect(),
+            ConnectionIdFormat::Tail => {
+                let skip = id.chars().count().saturating_sub(n);
+                id.chars().skip(skip).collect()
+            }
+            ConnectionIdFormat::Hash => format!("{:08x}", fnv1a_hash(id)),
+        }
+    }
+
+    /// Enables `--follow` if the replay source is not a regular file (e.g., a named pipe written by another process).
+    pub fn detect_follow(&mut self) {
+        if self.is_realtime_mode() {
+            return;
+        }
+        if let Ok(metadata) = std::fs::metadata(&self.sora_api_url) {
+            self.follow |= !metadata.is_file();
+        }
+    }
+
+    pub fn is_realtime_mode(&self) -> bool {
         self.sora_api_url.starts_with("http://") || self.sora_api_url.starts_with("https://")
     }
 }
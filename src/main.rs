@@ -1,29 +1,73 @@
 use clap::Parser;
 use orfail::OrFail;
 use sorastats::{poll, ui};
+use std::ffi::OsString;
 use std::path::PathBuf;
 
 /// WebRTC SFU Sora の統計情報ビューア
+///
+/// "@PATH" 形式の引数を指定すると、そのファイルの各行が一つずつの引数として展開される（例: "sorastats @sorastats.args"）。
+/// 各行は前後の空白を除いた上でそのまま一つの引数となり、クォートやエスケープの解釈は行われない
+/// （例えば "--alert" と "rtp.packets_lost/s>10" は別々の行に記述し、行内の空白や引用符は引数の一部として扱われる）。
+/// 空行と "#" で始まる行は無視され、ファイル内の "@PATH" はさらに展開されることはない。
+/// "@" で始まる引数をそのまま渡したい場合には、コマンドライン上で "@@" と記述する
 #[derive(Debug, Parser)]
 #[clap(version)]
 struct Args {
     #[clap(flatten)]
     options: sorastats::Options,
 
-    #[clap(hide = true, long)]
-    logfile: Option<PathBuf>,
+    /// ログの出力先ファイル
+    ///
+    /// TUI の表示を崩さないように、指定がない場合にはログは出力されない
+    #[clap(long, alias = "logfile", value_name = "PATH")]
+    log_file: Option<PathBuf>,
 
-    #[clap(hide = true,long, default_value_t = simplelog::LevelFilter::Info)]
-    loglevel: simplelog::LevelFilter,
+    /// ログの出力レベル（"off", "error", "warn", "info", "debug", "trace" のいずれか）
+    #[clap(long, alias = "loglevel", default_value_t = simplelog::LevelFilter::Info)]
+    log_level: simplelog::LevelFilter,
 
-    #[clap(hide = true, long)]
+    /// 指定された場合には、追記ではなく既存のログファイルを切り詰めてから書き込む
+    #[clap(long)]
     truncate_log: bool,
+
+    /// `--version` と合わせて指定すると、依存クレートのバージョンやビルドターゲットも表示する
+    #[clap(long, requires = "version")]
+    verbose: bool,
 }
 
 fn main() -> orfail::Result<()> {
-    let args = Args::parse();
+    let raw_args = expand_args_files().or_fail()?;
+    let mut args = match Args::try_parse_from(&raw_args) {
+        Ok(args) => args,
+        Err(e)
+            if e.kind() == clap::error::ErrorKind::DisplayVersion
+                && raw_args.iter().any(|arg| arg == "--verbose") =>
+        {
+            print_verbose_version();
+            return Ok(());
+        }
+        Err(e) => e.exit(),
+    };
 
     setup_logger(&args).or_fail()?;
+    if args.options.insecure {
+        log::warn!("TLS certificate verification is disabled (--insecure)");
+        eprintln!("WARNING: TLS certificate verification is disabled (--insecure). Use this only for development.");
+    }
+    args.options.expand_env_vars().or_fail()?;
+    args.options.detect_ascii_chart();
+    args.options.detect_follow();
+
+    if let Some([a, b]) = args.options.compare.as_deref() {
+        return sorastats::compare::run(&args.options, a, b).or_fail();
+    }
+    if args.options.csv_out.is_some() && !args.options.is_realtime_mode() {
+        return sorastats::csv::convert_record_file(args.options).or_fail();
+    }
+    if args.options.record_only {
+        return sorastats::daemon::run(args.options).or_fail();
+    }
 
     let rx = poll::StatsPoller::start_thread(args.options.clone()).or_fail()?;
     let app = ui::App::new(rx, args.options).or_fail()?;
@@ -35,8 +79,43 @@ fn main() -> orfail::Result<()> {
     result
 }
 
+/// Replaces each "@PATH" argument with the lines of the file (see the help of `Args` for the rules).
+fn expand_args_files() -> orfail::Result<Vec<OsString>> {
+    let mut expanded = Vec::new();
+    for (i, arg) in std::env::args_os().enumerate() {
+        let Some(path) = arg
+            .to_str()
+            .and_then(|s| s.strip_prefix('@'))
+            .filter(|_| i > 0)
+        else {
+            expanded.push(arg);
+            continue;
+        };
+        if path.starts_with('@') {
+            expanded.push(OsString::from(path));
+            continue;
+        }
+        let content = std::fs::read_to_string(path)
+            .or_fail_with(|e| format!("failed to read args file {path:?}: {e}"))?;
+        expanded.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(OsString::from),
+        );
+    }
+    Ok(expanded)
+}
+
+fn print_verbose_version() {
+    println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    println!("target: {}", env!("SORASTATS_BUILD_TARGET"));
+    println!("dependencies: {}", env!("SORASTATS_BUILD_DEPENDENCIES"));
+}
+
 fn setup_logger(args: &Args) -> orfail::Result<()> {
-    if let Some(logfile) = &args.logfile {
+    if let Some(logfile) = &args.log_file {
         let file = std::fs::OpenOptions::new()
             .append(!args.truncate_log)
             .truncate(args.truncate_log)
@@ -44,7 +123,7 @@ fn setup_logger(args: &Args) -> orfail::Result<()> {
             .write(true)
             .open(logfile)
             .or_fail_with(|e| format!("failed to open log file {logfile:?}: {e}"))?;
-        simplelog::WriteLogger::init(args.loglevel, Default::default(), file).or_fail()?;
+        simplelog::WriteLogger::init(args.log_level, Default::default(), file).or_fail()?;
     }
     Ok(())
 }
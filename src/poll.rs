@@ -1,68 +1,227 @@
-use crate::stats::{ConnectionStats, Stats};
+use crate::anonymize::Anonymizer;
+use crate::csv::CsvWriter;
+use crate::influx::InfluxWriter;
+use crate::record::{CompactDecoder, RecordEntry, RecordItem, RecordMetadata, Recorder};
+use crate::stats::{ConnectionStats, RequestStatus, Stats};
 use crate::Options;
 use orfail::OrFail;
+use std::collections::VecDeque;
 use std::fs::File;
-use std::io::{BufRead as _, BufReader, BufWriter, Write as _};
-use std::sync::mpsc;
+use std::io::{BufRead as _, BufReader, Read, Seek as _, SeekFrom};
+use std::sync::{mpsc, Arc};
 use std::time::{Duration, Instant, SystemTime};
 
 const SORA_API_HEADER_NAME: &str = "x-sora-target";
-const SORA_API_HEADER_VALUE: &str = "Sora_20171101.GetStatsAllConnections";
+pub const DEFAULT_SORA_TARGET: &str = "Sora_20171101.GetStatsAllConnections";
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(100);
 
-pub type StatsReceiver = mpsc::Receiver<Option<Stats>>;
+// Upper bound of the time to wait for the polling thread (e.g., blocked in an HTTP request) to finish.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Result of a poll (`Err` holds the error message of a failed request in realtime mode).
+pub type PollResult = Result<Stats, String>;
+
+#[derive(Debug)]
+pub struct StatsReceiver {
+    rx: mpsc::Receiver<PollResult>,
+
+    // Dropping this wakes up and stops the polling thread.
+    command_tx: Option<mpsc::Sender<PollerCommand>>,
+
+    // Disconnected when the polling thread has finished (only used in realtime mode).
+    finished_rx: Option<mpsc::Receiver<()>>,
+
+    // For replay mode
+    pub metadata: Option<RecordMetadata>,
+    pub schema_warning: Option<String>,
+}
+
+impl StatsReceiver {
+    pub fn recv(&self) -> Result<PollResult, mpsc::RecvError> {
+        self.rx.recv()
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<PollResult, mpsc::RecvTimeoutError> {
+        self.rx.recv_timeout(timeout)
+    }
+
+    /// Makes the polling thread poll the stats immediately (only effective in realtime polling mode).
+    pub fn poll_now(&self) {
+        if let Some(tx) = &self.command_tx {
+            let _ = tx.send(PollerCommand::PollNow);
+        }
+    }
+}
+
+impl Drop for StatsReceiver {
+    fn drop(&mut self) {
+        self.command_tx = None;
+
+        // Wait for the pending records to be written out.
+        if let Some(finished_rx) = &self.finished_rx {
+            if finished_rx.recv_timeout(SHUTDOWN_TIMEOUT) == Err(mpsc::RecvTimeoutError::Timeout) {
+                log::warn!("the polling thread did not finish in {SHUTDOWN_TIMEOUT:?}");
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PollerCommand {
+    PollNow,
+}
 
 #[derive(Debug)]
 enum Mode {
     Realtime {
-        tx: mpsc::Sender<Option<Stats>>,
+        tx: mpsc::Sender<PollResult>,
+        stream: Option<SseReader>,
     },
     Replay {
-        tx: mpsc::SyncSender<Option<Stats>>,
+        tx: mpsc::SyncSender<PollResult>,
         reader: BufReader<File>,
+        format: RecordFormat,
+        decoder: CompactDecoder,
+
+        // The first item read ahead from a non-seekable file (e.g., a named pipe).
+        pending: Option<RecordItem>,
     },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecordFormat {
+    /// One JSON entry per line (the format written by `--record`).
+    JsonLines,
+
+    /// A single JSON array of entries.
+    JsonArray,
+}
+
 #[derive(Debug)]
 pub struct StatsPoller {
     options: Options,
+    agent: ureq::Agent,
     mode: Mode,
     prev_request_time: Instant,
     prev_stats: Stats,
-    recorder: Option<BufWriter<File>>,
+
+    // Start time of the last successful HTTP request (for measuring the actual polling intervals).
+    prev_success_time: Option<Instant>,
+
+    // The last `--delta-window` snapshots (oldest first).
+    delta_window: VecDeque<Stats>,
+
+    recorder: Option<Recorder>,
+    influx_writer: Option<InfluxWriter>,
+    csv_writer: Option<CsvWriter>,
+    anonymizer: Option<Anonymizer>,
     start: Option<SystemTime>,
+
+    // Time of the first record in the file (the origin of `--replay-from` / `--replay-to` offsets).
+    replay_origin: Option<SystemTime>,
+
+    // Number of the record entries within the replay range read so far (for `--replay-stride`).
+    replay_read_count: usize,
+
+    command_rx: mpsc::Receiver<PollerCommand>,
+
+    // Must be dropped after the writers above (fields are dropped in declaration order).
+    _finished_tx: mpsc::Sender<()>,
 }
 
 impl StatsPoller {
     pub fn start_thread(options: Options) -> orfail::Result<StatsReceiver> {
         let recorder = options.create_recorder()?;
+        let influx_writer = options.create_influx_writer()?;
+        let csv_writer = options.create_csv_writer()?;
+        let agent = new_agent(&options).or_fail()?;
+        let anonymizer = options
+            .anonymize
+            .then(|| Anonymizer::new(&options.anonymize_keys));
 
         let (rx, mode) = if options.is_realtime_mode() {
             let (tx, rx) = mpsc::channel();
-            (rx, Mode::Realtime { tx })
+            (rx, Mode::Realtime { tx, stream: None })
         } else {
             let (tx, rx) = mpsc::sync_channel(0);
             let file = File::open(&options.sora_api_url).or_fail_with(|e| {
                 format!("failed to open record file {:?}: {e}", options.sora_api_url)
             })?;
-            let reader = BufReader::new(file);
-            (rx, Mode::Replay { tx, reader })
+            let mut reader = BufReader::new(file);
+            let format = detect_record_format(&mut reader).or_fail()?;
+            (
+                rx,
+                Mode::Replay {
+                    tx,
+                    reader,
+                    format,
+                    decoder: CompactDecoder::default(),
+                    pending: None,
+                },
+            )
         };
 
+        let (command_tx, command_rx) = mpsc::channel();
+        let (finished_tx, finished_rx) = mpsc::channel();
+        let realtime = options.is_realtime_mode();
         let mut poller = StatsPoller {
             options,
+            agent,
             mode,
             prev_request_time: Instant::now(),
             prev_stats: Stats::empty(),
+            prev_success_time: None,
+            delta_window: VecDeque::new(),
             recorder,
+            influx_writer,
+            csv_writer,
+            anonymizer,
             start: None,
+            replay_origin: None,
+            replay_read_count: 0,
+            command_rx,
+            _finished_tx: finished_tx,
+        };
+        let mut rx = StatsReceiver {
+            rx,
+            command_tx: Some(command_tx),
+            // In replay mode, the thread may be blocked in sending stats to the receiver.
+            finished_rx: realtime.then_some(finished_rx),
+            metadata: None,
+            schema_warning: None,
         };
         match &mut poller.mode {
             Mode::Realtime { .. } => {
-                poller.poll_once().or_fail()?;
+                poller.poll_once(true).or_fail()?;
             }
-            Mode::Replay { reader, .. } => {
-                if reader.get_mut().metadata().or_fail()?.len() == 0 {
+            Mode::Replay {
+                reader,
+                format,
+                decoder,
+                pending,
+                ..
+            } => {
+                let file_metadata = reader.get_mut().metadata().or_fail()?;
+                let follow = poller.options.follow;
+                if !file_metadata.is_file() {
+                    // As named pipes cannot be rewound, the first entry is kept if it is not the metadata.
+                    match read_record_entry(reader, *format, follow).or_fail()? {
+                        Some(RecordEntry::Metadata { metadata }) => rx.metadata = Some(metadata),
+                        Some(entry) => *pending = decoder.decode(entry).or_fail()?,
+                        None => {}
+                    }
+                } else if file_metadata.len() == 0 {
                     return Err(orfail::Failure::new("empty record file"));
+                } else if let Some(metadata) =
+                    read_record_metadata(reader, *format, follow).or_fail()?
+                {
+                    rx.schema_warning = peek_record_item(reader, *format)
+                        .or_fail()?
+                        .and_then(|item| metadata.check_schema_drift(&item));
+                    if let Some(warning) = &rx.schema_warning {
+                        log::warn!("{warning}");
+                    }
+                    rx.metadata = Some(metadata);
                 }
             }
         }
@@ -88,83 +247,232 @@ impl StatsPoller {
                 Ok(true) => {}
             }
         }
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.flush().or_fail() {
+                log::error!("failed to flush the record file: {e}");
+            }
+        }
+        if let Some(writer) = self.csv_writer.take() {
+            match writer.finish().or_fail() {
+                Ok(rows) => log::debug!("wrote {rows} rows to the CSV file"),
+                Err(e) => log::error!("failed to write the CSV file: {e}"),
+            }
+        }
     }
 
     fn run_once(&mut self) -> orfail::Result<bool> {
-        if matches!(self.mode, Mode::Realtime { .. }) {
+        let mut poll_now = false;
+        match self.command_rx.try_recv() {
+            Ok(PollerCommand::PollNow) => poll_now = true,
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => return Ok(false),
+        }
+        if !poll_now && matches!(self.mode, Mode::Realtime { stream: None, .. }) {
             let polling_interval = Duration::from_secs(self.options.polling_interval.get() as u64);
             if let Some(duration) = polling_interval.checked_sub(self.prev_request_time.elapsed()) {
-                std::thread::sleep(duration);
+                match self.command_rx.recv_timeout(duration) {
+                    Ok(PollerCommand::PollNow) => poll_now = true,
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(false),
+                }
             }
         }
-        self.poll_once().or_fail()
+        if poll_now {
+            log::debug!("poll immediately as requested");
+        }
+        self.poll_once(false).or_fail()
     }
 
-    fn poll_once(&mut self) -> orfail::Result<bool> {
+    fn poll_once(&mut self, is_initial_poll: bool) -> orfail::Result<bool> {
         self.prev_request_time = Instant::now();
-        let item = match &mut self.mode {
+        let mut request_status = None;
+        let mut item = match &mut self.mode {
+            Mode::Realtime { tx, stream } if self.options.stream => {
+                let values = match recv_stream_event(stream, &self.agent, &self.options) {
+                    Err(e) => {
+                        log::debug!("Failed to receive a stats event: {e}");
+                        *stream = None;
+                        return Ok(tx.send(Err(e.to_string())).is_ok());
+                    }
+                    Ok(values) => values,
+                };
+                let item = RecordItem {
+                    time: SystemTime::now(),
+                    values,
+                };
+                log::debug!(
+                    "Received a stats event (connections: {})",
+                    item.values.len()
+                );
+                item
+            }
             Mode::Realtime { tx, .. } => {
-                let values: Vec<serde_json::Value> = match ureq::post(&self.options.sora_api_url)
-                    .set(SORA_API_HEADER_NAME, SORA_API_HEADER_VALUE)
-                    .call()
-                {
+                let method = self.options.http_method.as_str();
+                let request = new_request(&self.agent, &self.options);
+                let result = if let Some(id) = &self.options.connection_id {
+                    request.send_json(serde_json::json!({ "connection_id": id }))
+                } else {
+                    request.call()
+                };
+                let values = match result {
+                    Err(ureq::Error::Status(_, response)) => read_stats_response(response),
+                    Err(e) => {
+                        log::debug!("HTTP {method} failed: {e}");
+                        // The URL is shown separately in the UI.
+                        let message = e.to_string();
+                        let url_prefix = format!("{}: ", self.options.sora_api_url);
+                        let message = message.strip_prefix(&url_prefix).unwrap_or(&message);
+                        return Ok(tx.send(Err(message.to_owned())).is_ok());
+                    }
+                    Ok(response) => {
+                        request_status = Some(RequestStatus {
+                            status: response.status(),
+                            latency: self.prev_request_time.elapsed(),
+                            interval: self
+                                .prev_success_time
+                                .map(|t| self.prev_request_time.duration_since(t)),
+                        });
+                        self.prev_success_time = Some(self.prev_request_time);
+                        read_stats_response(response)
+                    }
+                };
+                let values = match values {
+                    Ok(values) => values,
+                    Err(e) if is_initial_poll => {
+                        // The URL is likely to be wrong, so report the error without retrying.
+                        return Err(e);
+                    }
                     Err(e) => {
-                        log::debug!("HTTP POST failed: {e}");
-                        return Ok(tx.send(None).is_ok());
+                        log::debug!("Invalid stats response: {}", e.message);
+                        return Ok(tx.send(Err(e.message)).is_ok());
                     }
-                    Ok(response) => response.into_json().or_fail()?,
+                };
+                let values = if let Some(id) = &self.options.connection_id {
+                    select_connection(values, id)
+                } else {
+                    values
                 };
                 let item = RecordItem {
                     time: SystemTime::now(),
                     values,
                 };
-                if let Some(mut recorder) = self.recorder.as_mut() {
-                    #[allow(clippy::needless_borrows_for_generic_args)]
-                    serde_json::to_writer(&mut recorder, &item).or_fail()?;
-                    writeln!(recorder).or_fail()?;
-                    recorder.flush().or_fail()?;
-                }
                 log::debug!(
-                    "HTTP POST {} {}:{} (elapsed: {:?}, connections: {})",
+                    "HTTP {} {} {}:{} (elapsed: {:?}, connections: {})",
+                    method,
                     self.options.sora_api_url,
                     SORA_API_HEADER_NAME,
-                    SORA_API_HEADER_VALUE,
+                    self.options.sora_target,
                     self.prev_request_time.elapsed(),
                     item.values.len()
                 );
                 item
             }
-            Mode::Replay { reader, .. } => {
-                let mut buf = String::new();
-                let size = reader.read_line(&mut buf).or_fail()?;
-                if size == 0 {
+            Mode::Replay {
+                reader,
+                format,
+                decoder,
+                pending,
+                ..
+            } => loop {
+                let item = if let Some(item) = pending.take() {
+                    item
+                } else if let Some(item) =
+                    read_record_item(reader, *format, decoder, self.options.follow).or_fail()?
+                {
+                    item
+                } else {
                     return Ok(false); // EOF
+                };
+                let origin = *self.replay_origin.get_or_insert(item.time);
+                if let Some(to) = self.options.replay_to {
+                    if item.time > to.resolve(origin) {
+                        log::debug!("Reached the end of the replay range");
+                        return Ok(false);
+                    }
+                }
+                if let Some(from) = self.options.replay_from {
+                    if item.time < from.resolve(origin) {
+                        log::debug!("Skipped a record entry before the replay range");
+                        continue;
+                    }
+                }
+                let stride = self.options.replay_stride.get();
+                let is_kept = self.replay_read_count.is_multiple_of(stride);
+                self.replay_read_count += 1;
+                if !is_kept {
+                    log::debug!("Skipped a record entry by --replay-stride");
+                    continue;
                 }
-                let item: RecordItem = serde_json::from_str(&buf).or_fail()?;
                 log::debug!("Read a record entry (connections: {})", item.values.len());
 
-                item
-            }
+                break item;
+            },
         };
 
-        let start = if let Some(start) = self.start {
-            start
-        } else {
-            self.start = Some(item.time);
-            item.time
-        };
+        if let Some(anonymizer) = &mut self.anonymizer {
+            anonymizer.anonymize_values(&mut item.values);
+        }
+        if matches!(self.mode, Mode::Realtime { .. }) {
+            if let Some(recorder) = &mut self.recorder {
+                recorder.write_item(&item).or_fail()?;
+            }
+        }
 
-        let mut connections = Vec::new();
-        for value in item.values {
-            connections.push(ConnectionStats::new(value, &self.prev_stats)?);
+        let is_first = self.start.is_none();
+        let start = *self.start.get_or_insert(item.time);
+
+        let window = self.delta_window.make_contiguous();
+        let (connections, duplicate_connection_count) =
+            ConnectionStats::from_snapshot(item.values, window, item.time)?;
+        let unfiltered_connection_count = connections.len();
+        let mut connections = self.apply_connection_filters(connections);
+        let unfiltered_connection_count = (connections.len() < unfiltered_connection_count)
+            .then_some(unfiltered_connection_count);
+        let mut uncapped_connection_count = None;
+        if let Some(max) = self.options.max_connections {
+            if connections.len() > max.get() {
+                // Sort to keep the same connections across polls.
+                uncapped_connection_count = Some(connections.len());
+                connections.sort_by(|a, b| a.connection_id.cmp(&b.connection_id));
+                connections.truncate(max.get());
+            }
+        }
+        let clock_skew = (!is_first && item.time < self.prev_stats.time)
+            || connections.iter().any(|c| {
+                self.prev_stats
+                    .connections
+                    .get(&c.connection_id)
+                    .is_some_and(|prev| c.timestamp < prev.timestamp)
+            });
+        if clock_skew {
+            log::warn!("Clock skew detected: timestamps went backwards");
+        }
+        let timestamp = item
+            .time
+            .duration_since(start)
+            .unwrap_or_default()
+            .max(self.prev_stats.timestamp);
+        let mut stats = Stats::new(item.time, timestamp, connections);
+        stats.clock_skew = clock_skew;
+        stats.uncapped_connection_count = uncapped_connection_count;
+        stats.unfiltered_connection_count = unfiltered_connection_count;
+        stats.duplicate_connection_count = duplicate_connection_count;
+        stats.request_status = request_status;
+        if let Some(writer) = &mut self.influx_writer {
+            writer.write_stats(&stats).or_fail()?;
+        }
+        if let Some(writer) = &mut self.csv_writer {
+            writer.add_stats(&stats);
+        }
+        if self.delta_window.len() == self.options.delta_window.get() {
+            self.delta_window.pop_front();
         }
-        let connections = self.apply_connection_filters(connections);
-        let timestamp = item.time.duration_since(start).or_fail()?;
-        self.prev_stats = Stats::new(item.time, timestamp, connections);
+        self.delta_window.push_back(stats.clone());
+        self.prev_stats = stats;
 
         match &self.mode {
-            Mode::Realtime { tx } => Ok(tx.send(Some(self.prev_stats.clone())).is_ok()),
-            Mode::Replay { tx, .. } => Ok(tx.send(Some(self.prev_stats.clone())).is_ok()),
+            Mode::Realtime { tx, .. } => Ok(tx.send(Ok(self.prev_stats.clone())).is_ok()),
+            Mode::Replay { tx, .. } => Ok(tx.send(Ok(self.prev_stats.clone())).is_ok()),
         }
     }
 
@@ -172,18 +480,400 @@ impl StatsPoller {
         connections
             .into_iter()
             .filter(|c| {
-                c.items.iter().any(|(k, v)| {
-                    self.options
-                        .connection_filter
-                        .is_match(&format!("{}:{}", k, v.value))
-                })
+                c.filter_targets()
+                    .any(|s| self.options.connection_filter.is_match(&s))
             })
             .collect()
     }
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-struct RecordItem {
-    time: SystemTime,
-    values: Vec<serde_json::Value>,
+fn new_agent(options: &Options) -> orfail::Result<ureq::Agent> {
+    let mut builder = ureq::AgentBuilder::new().timeout_connect(options.connect_timeout);
+    if !options.stream {
+        builder = builder.timeout_read(options.read_timeout);
+    }
+    let client_auth = if let (Some(cert_path), Some(key_path)) =
+        (&options.client_cert, &options.client_key)
+    {
+        use rustls_pki_types::pem::PemObject as _;
+        use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+
+        let certs = CertificateDer::pem_file_iter(cert_path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .or_fail_with(|e| format!("failed to load client certificate {cert_path:?}: {e}"))?;
+        (!certs.is_empty()).or_fail_with(|()| {
+            format!("no certificates found in client certificate file {cert_path:?}")
+        })?;
+        let key = PrivateKeyDer::from_pem_file(key_path)
+            .or_fail_with(|e| format!("failed to load client key {key_path:?}: {e}"))?;
+        Some((certs, key))
+    } else {
+        None
+    };
+    if client_auth.is_some() || options.insecure {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config_builder = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .or_fail()?;
+        let config_builder = if options.insecure {
+            config_builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(NoServerCertVerification { provider }))
+        } else {
+            config_builder.with_root_certificates(rustls::RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            })
+        };
+        let config = if let Some((certs, key)) = client_auth {
+            config_builder
+                .with_client_auth_cert(certs, key)
+                .or_fail_with(|e| format!("invalid client certificate or key: {e}"))?
+        } else {
+            config_builder.with_no_client_auth()
+        };
+        builder = builder.tls_config(Arc::new(config));
+    }
+    Ok(builder.build())
+}
+
+/// Verifier that accepts any server certificate (see `--insecure`).
+///
+/// The handshake signatures are still verified, so this only skips the validation of the certificate chain and name.
+#[derive(Debug)]
+struct NoServerCertVerification {
+    provider: Arc<rustls::crypto::CryptoProvider>,
+}
+
+impl rustls::client::danger::ServerCertVerifier for NoServerCertVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls_pki_types::CertificateDer<'_>,
+        _intermediates: &[rustls_pki_types::CertificateDer<'_>],
+        _server_name: &rustls_pki_types::ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls_pki_types::UnixTime,
+    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::danger::ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls_pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls_pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+// With the "gzip" feature, ureq sends `Accept-Encoding: gzip` and transparently decodes the response body.
+fn new_request(agent: &ureq::Agent, options: &Options) -> ureq::Request {
+    let mut request = agent
+        .request(options.http_method.as_str(), &options.sora_api_url)
+        .set(SORA_API_HEADER_NAME, &options.sora_target);
+    for (name, value) in &options.headers {
+        request = request.set(name, value);
+    }
+    request
+}
+
+/// Keeps only the stats of the connection specified by `--connection-id`.
+fn select_connection(values: Vec<serde_json::Value>, id: &str) -> Vec<serde_json::Value> {
+    values
+        .into_iter()
+        .filter_map(|mut value| {
+            // Single connection responses may omit the ID.
+            let obj = value.as_object_mut()?;
+            obj.entry("connection_id")
+                .or_insert_with(|| serde_json::Value::String(id.to_owned()));
+            (obj.get("connection_id")?.as_str() == Some(id)).then_some(value)
+        })
+        .collect()
+}
+
+/// Reads a `GetStatsAllConnections` response, checking its content type and shape before parsing.
+fn read_stats_response(response: ureq::Response) -> orfail::Result<Vec<serde_json::Value>> {
+    let status = response.status();
+    let content_type = response.content_type().to_owned();
+    let body = response.into_string().or_fail()?;
+    match serde_json::from_str(&body) {
+        Ok(serde_json::Value::Array(values)) if status < 400 => {
+            if let Some(i) = values.iter().position(|v| !v.is_object()) {
+                return Err(orfail::Failure::new(format!(
+                    "expected JSON array of connections, but the element at index {i} is not a JSON object (status {status})"
+                )));
+            }
+            Ok(values)
+        }
+        Ok(value @ serde_json::Value::Object(_)) if status < 400 => {
+            // e.g., The response of `GetStatsConnection` (see `--connection-id`).
+            Ok(vec![value])
+        }
+        Ok(_) if content_type.contains("json") => Err(orfail::Failure::new(format!(
+            "expected JSON array of connections, got other JSON value (status {status})"
+        ))),
+        _ => Err(orfail::Failure::new(format!(
+            "expected JSON array of connections, got {content_type} (status {status})"
+        ))),
+    }
+}
+
+fn recv_stream_event(
+    stream: &mut Option<SseReader>,
+    agent: &ureq::Agent,
+    options: &Options,
+) -> orfail::Result<Vec<serde_json::Value>> {
+    if stream.is_none() {
+        let url = &options.sora_api_url;
+        let response = new_request(agent, options)
+            .set("accept", "text/event-stream")
+            .call()
+            .or_fail()?;
+        log::debug!("Opened a stats stream: {url}");
+        *stream = Some(SseReader::new(response.into_reader()));
+    }
+
+    let reader = stream.as_mut().or_fail()?;
+    let data = reader
+        .next_event_data()
+        .or_fail()?
+        .or_fail_with(|_| "stats stream closed".to_owned())?;
+    let values = serde_json::from_str(&data)
+        .or_fail_with(|e| format!("invalid stats event {data:?}: {e}"))?;
+    Ok(values)
+}
+
+/// Reader for a server-sent events (SSE) stream.
+struct SseReader {
+    reader: BufReader<Box<dyn Read + Send + Sync>>,
+}
+
+impl SseReader {
+    fn new(reader: Box<dyn Read + Send + Sync>) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+        }
+    }
+
+    fn next_event_data(&mut self) -> orfail::Result<Option<String>> {
+        let mut data: Option<String> = None;
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line).or_fail()? == 0 {
+                return Ok(None);
+            }
+
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                if data.is_some() {
+                    return Ok(data);
+                }
+            } else if let Some(value) = line.strip_prefix("data:") {
+                // Other fields ("event", "id", "retry") and comments are not used.
+                let value = value.strip_prefix(' ').unwrap_or(value);
+                if let Some(data) = &mut data {
+                    data.push('\n');
+                    data.push_str(value);
+                } else {
+                    data = Some(value.to_owned());
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for SseReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("SseReader").finish_non_exhaustive()
+    }
+}
+
+/// Reads all the entries of a record file without starting a poller thread.
+pub fn read_record_file(
+    path: &std::path::Path,
+) -> orfail::Result<(Option<RecordMetadata>, Vec<RecordItem>)> {
+    let file =
+        File::open(path).or_fail_with(|e| format!("failed to open record file {path:?}: {e}"))?;
+    let mut reader = BufReader::new(file);
+    let format = detect_record_format(&mut reader).or_fail()?;
+    let metadata = read_record_metadata(&mut reader, format, false).or_fail()?;
+    let mut decoder = CompactDecoder::default();
+    let mut items = Vec::new();
+    while let Some(item) = read_record_item(&mut reader, format, &mut decoder, false).or_fail()? {
+        items.push(item);
+    }
+    Ok((metadata, items))
+}
+
+fn detect_record_format(reader: &mut BufReader<File>) -> orfail::Result<RecordFormat> {
+    skip_whitespaces(reader).or_fail()?;
+    if reader.fill_buf().or_fail()?.first() == Some(&b'[') {
+        Ok(RecordFormat::JsonArray)
+    } else {
+        Ok(RecordFormat::JsonLines)
+    }
+}
+
+fn skip_whitespaces(reader: &mut BufReader<File>) -> orfail::Result<()> {
+    loop {
+        let buf = reader.fill_buf().or_fail()?;
+        let n = buf.iter().take_while(|b| b.is_ascii_whitespace()).count();
+        let reached_end = n < buf.len() || buf.is_empty();
+        reader.consume(n);
+        if reached_end {
+            return Ok(());
+        }
+    }
+}
+
+fn read_record_metadata(
+    reader: &mut BufReader<File>,
+    format: RecordFormat,
+    follow: bool,
+) -> orfail::Result<Option<RecordMetadata>> {
+    let position = reader.stream_position().or_fail()?;
+    match read_record_entry(reader, format, follow).or_fail()? {
+        Some(RecordEntry::Metadata { metadata }) => Ok(Some(metadata)),
+        _ => {
+            // Old record files do not have the metadata header.
+            reader.seek(SeekFrom::Start(position)).or_fail()?;
+            Ok(None)
+        }
+    }
+}
+
+fn peek_record_item(
+    reader: &mut BufReader<File>,
+    format: RecordFormat,
+) -> orfail::Result<Option<RecordItem>> {
+    let position = reader.stream_position().or_fail()?;
+    let mut decoder = CompactDecoder::default();
+    let item = loop {
+        let entry = match format {
+            RecordFormat::JsonLines => {
+                let mut buf = String::new();
+                while reader.read_line(&mut buf).or_fail()? > 0 && buf.trim().is_empty() {
+                    buf.clear();
+                }
+                if buf.ends_with('\n') {
+                    Some(serde_json::from_str(&buf).or_fail()?)
+                } else {
+                    // The line has not been completely written yet.
+                    None
+                }
+            }
+            RecordFormat::JsonArray => read_json_array_entry(reader).or_fail()?,
+        };
+        match entry {
+            None | Some(RecordEntry::Metadata { .. }) => break None,
+            // Compact record files have a dictionary entry before the first item.
+            Some(entry) => {
+                if let Some(item) = decoder.decode(entry).or_fail()? {
+                    break Some(item);
+                }
+            }
+        }
+    };
+    reader.seek(SeekFrom::Start(position)).or_fail()?;
+    Ok(item)
+}
+
+fn read_record_item(
+    reader: &mut BufReader<File>,
+    format: RecordFormat,
+    decoder: &mut CompactDecoder,
+    follow: bool,
+) -> orfail::Result<Option<RecordItem>> {
+    loop {
+        let Some(entry) = read_record_entry(reader, format, follow).or_fail()? else {
+            return Ok(None);
+        };
+        if let Some(item) = decoder.decode(entry).or_fail()? {
+            return Ok(Some(item));
+        }
+    }
+}
+
+fn read_record_entry(
+    reader: &mut BufReader<File>,
+    format: RecordFormat,
+    follow: bool,
+) -> orfail::Result<Option<RecordEntry>> {
+    match format {
+        RecordFormat::JsonLines => loop {
+            let mut buf = String::new();
+            let size = read_record_line(reader, &mut buf, follow).or_fail()?;
+            if size == 0 {
+                return Ok(None);
+            }
+            if buf.trim().is_empty() {
+                // Interrupted writers may leave blank lines behind.
+                log::debug!("Skipped a blank line in the record file");
+                continue;
+            }
+            return Ok(Some(serde_json::from_str(&buf).or_fail()?));
+        },
+        RecordFormat::JsonArray => read_json_array_entry(reader).or_fail(),
+    }
+}
+
+/// Reads the next element of a JSON array record file (`--follow` is not supported for this format).
+fn read_json_array_entry(reader: &mut BufReader<File>) -> orfail::Result<Option<RecordEntry>> {
+    loop {
+        skip_whitespaces(reader).or_fail()?;
+        match reader.fill_buf().or_fail()?.first() {
+            None | Some(b']') => return Ok(None),
+            Some(b'[' | b',') => reader.consume(1),
+            Some(_) => {
+                // As the elements are JSON objects, the deserializer does not read beyond the closing '}'.
+                let entry = serde_json::Deserializer::from_reader(&mut *reader)
+                    .into_iter()
+                    .next()
+                    .or_fail()?
+                    .or_fail()?;
+                return Ok(Some(entry));
+            }
+        }
+    }
+}
+
+fn read_record_line(
+    reader: &mut BufReader<File>,
+    buf: &mut String,
+    follow: bool,
+) -> orfail::Result<usize> {
+    loop {
+        reader.read_line(buf).or_fail()?;
+        if !follow || buf.ends_with('\n') {
+            return Ok(buf.len());
+        }
+
+        // Wait for new lines to be appended (like `tail -f`).
+        std::thread::sleep(FOLLOW_INTERVAL);
+    }
 }
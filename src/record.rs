@@ -0,0 +1,347 @@
+use crate::stats::{self, StatsItemKey};
+use crate::Options;
+use orfail::OrFail;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write as _};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const SCHEMA_DRIFT_THRESHOLD: f64 = 0.1;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecordItem {
+    pub time: SystemTime,
+    pub values: Vec<serde_json::Value>,
+}
+
+/// Format of the record files written by `--record`.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFileFormat {
+    /// One raw Sora stats JSON per line.
+    #[default]
+    Jsonl,
+
+    /// Key-dictionary-encoded JSON Lines (see `CompactEncoder`).
+    Compact,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct RecordMetadata {
+    #[serde(default)]
+    pub version: String,
+
+    /// Schema fingerprint (the sorted set of the stats keys seen in the first record).
+    #[serde(default)]
+    pub stats_keys: BTreeSet<StatsItemKey>,
+
+    /// Value of the `x-sora-target` header used to fetch the stats.
+    #[serde(default)]
+    pub sora_target: Option<String>,
+
+    /// Human-readable label of the recording session (see `--label`).
+    #[serde(default)]
+    pub label: Option<String>,
+
+    /// Freeform notes on the recording session (see `--note`).
+    #[serde(default)]
+    pub note: Option<String>,
+
+    #[serde(default)]
+    pub format: RecordFileFormat,
+}
+
+impl RecordMetadata {
+    pub fn new(options: &Options) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            stats_keys: BTreeSet::new(),
+            sora_target: Some(options.sora_target.clone()),
+            label: options.label.clone(),
+            note: options.note.clone(),
+            format: options.record_format,
+        }
+    }
+
+    pub fn check_schema_drift(&self, item: &RecordItem) -> Option<String> {
+        if self.stats_keys.is_empty() {
+            return None;
+        }
+
+        let keys = stats::collect_stats_keys(&item.values);
+        let missing = self.stats_keys.difference(&keys).count();
+        let added = keys.difference(&self.stats_keys).count();
+        let total = self.stats_keys.union(&keys).count();
+        if (missing + added) as f64 / total as f64 <= SCHEMA_DRIFT_THRESHOLD {
+            return None;
+        }
+
+        let recorded_by = if self.version.is_empty() {
+            String::new()
+        } else {
+            format!(" (recorded by v{})", self.version)
+        };
+        Some(format!(
+            "Stats schema differs from the record{recorded_by}: {missing} keys missing, {added} keys added"
+        ))
+    }
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum RecordEntry {
+    Metadata {
+        metadata: RecordMetadata,
+    },
+    Item(RecordItem),
+
+    /// Snapshot encoded by `CompactEncoder` (`[SCHEMA_INDEX, VALUE...]` per connection).
+    CompactItem {
+        t: (u64, u32),
+        c: Vec<Vec<Value>>,
+    },
+
+    /// Value paths and schemas newly interned by `CompactEncoder`.
+    Dictionary {
+        paths: Vec<ValuePath>,
+        schemas: Vec<Vec<usize>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Location of a leaf value (a scalar, or an empty object or array) in a connection stats JSON.
+pub type ValuePath = Vec<PathSegment>;
+
+/// Encoder of the compact record format.
+///
+/// Each connection stats JSON is flattened into leaf values.
+/// The paths of the leaves are interned once, and so are the lists of the paths ("schemas"),
+/// so a connection in a snapshot is written as a schema index followed by the leaf values.
+#[derive(Debug, Default)]
+pub struct CompactEncoder {
+    paths: HashMap<ValuePath, usize>,
+    schemas: HashMap<Vec<usize>, usize>,
+}
+
+impl CompactEncoder {
+    /// Returns the dictionary entry (if anything was newly interned) and the entry of the item.
+    pub fn encode(&mut self, item: &RecordItem) -> (Option<RecordEntry>, RecordEntry) {
+        let mut new_paths = Vec::new();
+        let mut new_schemas = Vec::new();
+        let mut connections = Vec::with_capacity(item.values.len());
+        for value in &item.values {
+            let mut leaves = Vec::new();
+            flatten_value(value, &mut Vec::new(), &mut leaves);
+
+            let mut schema = Vec::with_capacity(leaves.len());
+            let mut connection = Vec::with_capacity(leaves.len() + 1);
+            connection.push(Value::Null); // Replaced with the schema index later.
+            for (path, leaf) in leaves {
+                let next_index = self.paths.len();
+                let index = *self.paths.entry(path).or_insert_with_key(|path| {
+                    new_paths.push(path.clone());
+                    next_index
+                });
+                schema.push(index);
+                connection.push(leaf);
+            }
+            let next_index = self.schemas.len();
+            let index = *self.schemas.entry(schema).or_insert_with_key(|schema| {
+                new_schemas.push(schema.clone());
+                next_index
+            });
+            connection[0] = Value::from(index);
+            connections.push(connection);
+        }
+
+        let elapsed = item.time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let dictionary =
+            (!new_paths.is_empty() || !new_schemas.is_empty()).then_some(RecordEntry::Dictionary {
+                paths: new_paths,
+                schemas: new_schemas,
+            });
+        let item = RecordEntry::CompactItem {
+            t: (elapsed.as_secs(), elapsed.subsec_nanos()),
+            c: connections,
+        };
+        (dictionary, item)
+    }
+}
+
+/// Decoder of record entries, which restores the items written by `CompactEncoder`.
+#[derive(Debug, Default)]
+pub struct CompactDecoder {
+    paths: Vec<ValuePath>,
+    schemas: Vec<Vec<usize>>,
+}
+
+impl CompactDecoder {
+    /// Returns `None` if the entry is not a stats snapshot (e.g., metadata or a dictionary).
+    pub fn decode(&mut self, entry: RecordEntry) -> orfail::Result<Option<RecordItem>> {
+        match entry {
+            RecordEntry::Metadata { .. } => {
+                log::debug!("Skipped a record metadata entry");
+                Ok(None)
+            }
+            RecordEntry::Item(item) => Ok(Some(item)),
+            RecordEntry::Dictionary { paths, schemas } => {
+                self.paths.extend(paths);
+                self.schemas.extend(schemas);
+                Ok(None)
+            }
+            RecordEntry::CompactItem {
+                t: (secs, nanos),
+                c,
+            } => {
+                let mut values = Vec::with_capacity(c.len());
+                for connection in c {
+                    let mut leaves = connection.into_iter();
+                    let schema = leaves
+                        .next()
+                        .and_then(|v| v.as_u64())
+                        .and_then(|i| self.schemas.get(i as usize))
+                        .or_fail_with(|()| "unknown schema in a compact record entry".to_owned())?;
+                    (schema.len() == leaves.len()).or_fail_with(|()| {
+                        "value count mismatch in a compact record entry".to_owned()
+                    })?;
+                    let mut value = Value::Null;
+                    for (&index, leaf) in schema.iter().zip(leaves) {
+                        let path = self.paths.get(index).or_fail_with(|()| {
+                            "unknown path in a compact record entry".to_owned()
+                        })?;
+                        insert_value(&mut value, path, leaf);
+                    }
+                    values.push(value);
+                }
+                Ok(Some(RecordItem {
+                    time: UNIX_EPOCH + Duration::new(secs, nanos),
+                    values,
+                }))
+            }
+        }
+    }
+}
+
+fn flatten_value(value: &Value, path: &mut ValuePath, leaves: &mut Vec<(ValuePath, Value)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (k, v) in map {
+                path.push(PathSegment::Key(k.clone()));
+                flatten_value(v, path, leaves);
+                path.pop();
+            }
+        }
+        Value::Array(array) if !array.is_empty() => {
+            for (i, v) in array.iter().enumerate() {
+                path.push(PathSegment::Index(i));
+                flatten_value(v, path, leaves);
+                path.pop();
+            }
+        }
+        _ => leaves.push((path.clone(), value.clone())),
+    }
+}
+
+fn insert_value(target: &mut Value, path: &[PathSegment], leaf: Value) {
+    let Some((head, tail)) = path.split_first() else {
+        *target = leaf;
+        return;
+    };
+    match head {
+        PathSegment::Key(key) => {
+            if !target.is_object() {
+                *target = Value::Object(Default::default());
+            }
+            if let Value::Object(map) = target {
+                insert_value(map.entry(key.clone()).or_insert(Value::Null), tail, leaf);
+            }
+        }
+        PathSegment::Index(i) => {
+            if !target.is_array() {
+                *target = Value::Array(Vec::new());
+            }
+            if let Value::Array(array) = target {
+                if array.len() <= *i {
+                    array.resize(*i + 1, Value::Null);
+                }
+                insert_value(&mut array[*i], tail, leaf);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Recorder {
+    writer: BufWriter<File>,
+    flush_interval: Duration,
+    last_flush_time: Instant,
+
+    // Written along with the first item.
+    metadata: Option<RecordMetadata>,
+
+    // Only used for the compact format.
+    encoder: Option<CompactEncoder>,
+}
+
+impl Recorder {
+    pub fn new(file: File, metadata: RecordMetadata, flush_interval: Duration) -> Self {
+        Self {
+            writer: BufWriter::new(file),
+            flush_interval,
+            last_flush_time: Instant::now(),
+            encoder: (metadata.format == RecordFileFormat::Compact).then(CompactEncoder::default),
+            metadata: Some(metadata),
+        }
+    }
+
+    pub fn write_item(&mut self, item: &RecordItem) -> orfail::Result<()> {
+        if let Some(mut metadata) = self.metadata.take() {
+            metadata.stats_keys = stats::collect_stats_keys(&item.values);
+            self.write_entry(&RecordEntry::Metadata { metadata })
+                .or_fail()?;
+        }
+
+        if let Some(encoder) = &mut self.encoder {
+            let (dictionary, item) = encoder.encode(item);
+            if let Some(dictionary) = dictionary {
+                self.write_entry(&dictionary).or_fail()?;
+            }
+            self.write_entry(&item).or_fail()?;
+        } else {
+            serde_json::to_writer(&mut self.writer, item).or_fail()?;
+            writeln!(self.writer).or_fail()?;
+        }
+        if self.last_flush_time.elapsed() >= self.flush_interval {
+            self.writer.flush().or_fail()?;
+            self.last_flush_time = Instant::now();
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> orfail::Result<()> {
+        self.writer.flush().or_fail()
+    }
+
+    fn write_entry(&mut self, entry: &RecordEntry) -> orfail::Result<()> {
+        serde_json::to_writer(&mut self.writer, entry).or_fail()?;
+        writeln!(self.writer).or_fail()?;
+        Ok(())
+    }
+}
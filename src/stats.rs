@@ -5,25 +5,59 @@ use std::time::{Duration, SystemTime};
 
 pub type StatsItemKey = String;
 pub type ConnectionId = String;
+pub type Timestamp = chrono::DateTime<chrono::FixedOffset>;
 
 #[derive(Debug, Clone)]
 pub struct ConnectionStatsItemValue {
     pub value: StatsItemValue,
+    pub delta: Option<f64>,
     pub delta_per_sec: Option<f64>,
+
+    /// Value that `delta_per_sec` is relative to (i.e., the value in the oldest snapshot of the delta window).
+    pub base_value: Option<f64>,
+
+    pub transitions: ValueTransitions,
 }
 
 impl ConnectionStatsItemValue {
-    pub fn format_value(&self) -> String {
-        if let StatsItemValue::Number(v) = self.value {
-            format_u64(v as u64)
+    pub fn format_value(&self, ctx: FormatContext) -> String {
+        match self.value {
+            StatsItemValue::Number(v) => ctx.format_number(v, 0),
+            StatsItemValue::UInt(v) if ctx.is_scaled() => ctx.format_number(v as f64, 0),
+            StatsItemValue::Int(v) if ctx.is_scaled() => ctx.format_number(v as f64, 0),
+            StatsItemValue::UInt(v) => format_u64(v),
+            StatsItemValue::Int(v) => format_i128(i128::from(v)),
+            _ => self.value.to_string(),
+        }
+    }
+
+    pub fn format_delta_per_sec(&self, ctx: FormatContext) -> String {
+        format_delta_per_sec(self.delta_per_sec, self.base_value, ctx)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ValueTransitions {
+    pub count: usize,
+    pub last_changed: Option<Timestamp>,
+}
+
+impl ValueTransitions {
+    fn next(&self, prev: &StatsItemValue, curr: &StatsItemValue, now: Timestamp) -> Self {
+        if prev == curr {
+            self.clone()
         } else {
-            self.value.to_string()
+            Self {
+                count: self.count + 1,
+                last_changed: Some(now),
+            }
         }
     }
 
-    pub fn format_delta_per_sec(&self) -> String {
-        if let Some(v) = self.delta_per_sec {
-            format_u64(v.round() as u64)
+    pub fn format_last_changed(&self, now: Timestamp) -> String {
+        if let Some(t) = self.last_changed {
+            let elapsed = (now - t).num_seconds().max(0) as u64;
+            format!("{}s ago", format_u64(elapsed))
         } else {
             String::new()
         }
@@ -33,28 +67,215 @@ impl ConnectionStatsItemValue {
 #[derive(Debug, Clone)]
 pub struct AggregatedStatsItemValue {
     pub value_sum: Option<f64>,
+
+    /// Exact sum of the values (available only if all of the values are integers).
+    pub integer_value_sum: Option<i128>,
+
+    pub delta: Option<f64>,
     pub delta_per_sec: Option<f64>,
+
+    /// Sum of the base values of the connections that contribute to `delta_per_sec`.
+    pub base_value_sum: Option<f64>,
 }
 
 impl AggregatedStatsItemValue {
-    pub fn format_value_sum(&self) -> String {
-        if let Some(v) = self.value_sum {
-            format_u64(v.round() as u64)
+    pub fn format_value_sum(&self, ctx: FormatContext) -> String {
+        if let Some(v) = self.integer_value_sum.filter(|_| !ctx.is_scaled()) {
+            format_i128(v)
+        } else if let Some(v) = self.value_sum {
+            ctx.format_number(v, 0)
         } else {
             String::new()
         }
     }
 
-    pub fn format_delta_per_sec(&self) -> String {
-        if let Some(v) = self.delta_per_sec {
-            format_u64(v.round() as u64)
-        } else {
-            String::new()
+    /// Formats the sum divided by `connection_count` (i.e., the per-connection average).
+    pub fn format_value_avg(&self, connection_count: usize, ctx: FormatContext) -> String {
+        match self.value_sum {
+            Some(v) if connection_count > 0 => {
+                ctx.format_number(v / connection_count as f64, ctx.delta_precision)
+            }
+            _ => String::new(),
+        }
+    }
+
+    pub fn format_delta_per_sec(&self, ctx: FormatContext) -> String {
+        format_delta_per_sec(self.delta_per_sec, self.base_value_sum, ctx)
+    }
+}
+
+fn format_delta_per_sec(
+    delta_per_sec: Option<f64>,
+    base: Option<f64>,
+    ctx: FormatContext,
+) -> String {
+    match (delta_per_sec, base) {
+        (Some(v), Some(base)) if ctx.percent_delta => {
+            if base != 0.0 {
+                let percent = v / base.abs() * 100.0;
+                format!("{}%", ctx.format_f64(percent, ctx.delta_precision.max(1)))
+            } else if v > 0.0 {
+                "∞".to_owned()
+            } else if v < 0.0 {
+                "-∞".to_owned()
+            } else {
+                "-".to_owned()
+            }
+        }
+        (Some(v), _) => ctx.format_number(v, ctx.delta_precision),
+        (None, _) => String::new(),
+    }
+}
+
+/// Options that affect how stats values are formatted.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FormatContext {
+    /// Number of decimal places of deltas (zero means rounding to an integer).
+    pub delta_precision: usize,
+
+    pub rounding: RoundingMode,
+
+    /// Unit of the formatted stats item (`None` means a plain number).
+    pub unit: Option<Unit>,
+
+    /// Whether to shorten plain counts with k / M / G suffixes (e.g., "1.2k").
+    pub compact_numbers: bool,
+
+    /// Whether to format deltas per second as percentages of the base values (e.g., "1.5%").
+    pub percent_delta: bool,
+}
+
+impl FormatContext {
+    /// Returns `true` if integers are not formatted as-is (i.e., with all digits).
+    fn is_scaled(self) -> bool {
+        self.compact_numbers || !matches!(self.unit, None | Some(Unit::Count))
+    }
+
+    /// Formats `v` in a human-readable form according to the unit.
+    pub fn format_number(self, v: f64, precision: usize) -> String {
+        match self.unit {
+            None | Some(Unit::Count) if self.compact_numbers => {
+                const SUFFIXES: [&str; 6] = ["", "k", "M", "G", "T", "P"];
+                let precision = |n| if n == 0 { precision } else { 1 };
+                let (v, n) = self.scale_down(v, 1000.0, SUFFIXES.len() - 1, precision);
+                format!("{}{}", self.format_f64(v, precision(n)), SUFFIXES[n])
+            }
+            None | Some(Unit::Count) => self.format_f64(v, precision),
+            Some(Unit::Bytes) => {
+                const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+                let precision = |n| if n == 0 { precision } else { precision.max(1) };
+                let (v, n) = self.scale_down(v, 1024.0, UNITS.len() - 1, precision);
+                format!("{} {}", self.format_f64(v, precision(n)), UNITS[n])
+            }
+            Some(Unit::Seconds) => {
+                if v.abs() >= 1.0 {
+                    format!("{} s", self.format_f64(v, precision.max(2)))
+                } else if v.abs() >= 0.001 || v == 0.0 {
+                    format!("{} ms", self.format_f64(v * 1e3, precision))
+                } else {
+                    format!("{} µs", self.format_f64(v * 1e6, precision))
+                }
+            }
+            Some(Unit::Ratio) => format!("{}%", self.format_f64(v * 100.0, precision.max(1))),
+        }
+    }
+
+    /// Repeatedly divides `v` by `base` while it would still be displayed as `base` or more,
+    /// and returns the divided value with the number of divisions (at most `max_divisions`).
+    ///
+    /// `precision(n)` is the number of decimal places used to display the value after `n` divisions.
+    fn scale_down(
+        self,
+        mut v: f64,
+        base: f64,
+        max_divisions: usize,
+        precision: impl Fn(usize) -> usize,
+    ) -> (f64, usize) {
+        let mut n = 0;
+        // Compare after rounding so that e.g. 999,950 becomes "1.0M" instead of "1,000.0k".
+        while n < max_divisions && self.round(v, precision(n)).abs() >= base {
+            v /= base;
+            n += 1;
+        }
+        (v, n)
+    }
+
+    /// Rounds `v` to `precision` decimal places.
+    fn round(self, v: f64, precision: usize) -> f64 {
+        let precision = precision.min(crate::MAX_DELTA_PRECISION as usize);
+        self.round_scaled(v, precision) / 10f64.powi(precision as i32)
+    }
+
+    /// Returns `v * 10^precision` rounded to an integral value.
+    fn round_scaled(self, v: f64, precision: usize) -> f64 {
+        let scaled = v * 10f64.powi(precision as i32);
+        match self.rounding {
+            RoundingMode::HalfUp => scaled.round(),
+            RoundingMode::HalfEven => scaled.round_ties_even(),
+        }
+    }
+
+    /// Formats `v` with digit grouping after rounding it to `precision` decimal places.
+    pub fn format_f64(self, v: f64, precision: usize) -> String {
+        // Larger precisions would overflow the integer arithmetic below.
+        let precision = precision.min(crate::MAX_DELTA_PRECISION as usize);
+        let rounded = self.round_scaled(v, precision);
+        if !rounded.is_finite() {
+            return v.to_string();
+        }
+
+        // `as` saturates on overflow, which is acceptable for display purposes.
+        let n = rounded as i128;
+        let sign = if n < 0 { "-" } else { "" };
+        let n = n.unsigned_abs();
+        if precision == 0 {
+            return format!("{sign}{}", format_u128(n));
         }
+        let scale = 10u128.pow(precision as u32);
+        format!("{sign}{}.{:0precision$}", format_u128(n / scale), n % scale)
+    }
+}
+
+/// Unit of a stats item value (see `--unit`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Unit {
+    /// Number of bytes (e.g., "1.5 MiB").
+    Bytes,
+
+    /// Seconds (e.g., "13 ms").
+    Seconds,
+
+    /// Ratio between 0 and 1 (e.g., "1.3%").
+    Ratio,
+
+    /// Plain count (same as the default formatting).
+    Count,
+}
+
+/// How to round values to the displayed precision.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum RoundingMode {
+    /// Rounds half away from zero.
+    #[default]
+    HalfUp,
+
+    /// Rounds half to even (a.k.a. banker's rounding).
+    HalfEven,
+}
+
+pub fn format_u64(n: u64) -> String {
+    format_u128(u128::from(n))
+}
+
+fn format_i128(n: i128) -> String {
+    if n < 0 {
+        format!("-{}", format_u128(n.unsigned_abs()))
+    } else {
+        format_u128(n as u128)
     }
 }
 
-pub fn format_u64(mut n: u64) -> String {
+fn format_u128(mut n: u128) -> String {
     let mut s = Vec::new();
     for i in 0.. {
         if i % 3 == 0 && i != 0 {
@@ -74,16 +295,36 @@ pub fn format_u64(mut n: u64) -> String {
 #[derive(Debug, Clone)]
 pub enum StatsItemValue {
     Number(f64),
+    UInt(u64),
+    Int(i64),
     Bool(bool),
     String(String),
 }
 
 impl StatsItemValue {
     pub fn as_f64(&self) -> Option<f64> {
-        if let Self::Number(v) = self {
-            Some(*v)
+        match self {
+            Self::Number(v) => Some(*v),
+            Self::UInt(v) => Some(*v as f64),
+            Self::Int(v) => Some(*v as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            Self::UInt(v) => Some(i128::from(*v)),
+            Self::Int(v) => Some(i128::from(*v)),
+            _ => None,
+        }
+    }
+
+    fn delta_from(&self, prev: &Self) -> Option<f64> {
+        if let (Some(v1), Some(v0)) = (self.as_i128(), prev.as_i128()) {
+            // Calculate the difference before converting to f64 to avoid losing precision.
+            Some((v1 - v0) as f64)
         } else {
-            None
+            Some(self.as_f64()? - prev.as_f64()?)
         }
     }
 }
@@ -92,6 +333,9 @@ impl PartialEq for StatsItemValue {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Number(x), Self::Number(y)) => x == y,
+            (Self::UInt(_) | Self::Int(_), Self::UInt(_) | Self::Int(_)) => {
+                self.as_i128() == other.as_i128()
+            }
             (Self::Bool(x), Self::Bool(y)) => x == y,
             (Self::String(x), Self::String(y)) => x == y,
             _ => false,
@@ -105,6 +349,8 @@ impl std::fmt::Display for StatsItemValue {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Self::Number(x) => write!(f, "{x}"),
+            Self::UInt(x) => write!(f, "{x}"),
+            Self::Int(x) => write!(f, "{x}"),
             Self::Bool(x) => write!(f, "{x}"),
             Self::String(x) => write!(f, "{x}"),
         }
@@ -117,20 +363,31 @@ pub struct AggregatedStats {
 }
 
 impl AggregatedStats {
-    fn new(connections: &[ConnectionStats]) -> Self {
+    fn new<'a>(connections: impl IntoIterator<Item = &'a ConnectionStats>) -> Self {
         let mut keys = BTreeSet::new();
         let mut sums = BTreeMap::<_, f64>::new();
+        let mut integer_sums = BTreeMap::<_, Option<i128>>::new();
         let mut deltas = BTreeMap::<_, f64>::new();
+        let mut deltas_per_sec = BTreeMap::<_, f64>::new();
+        let mut base_sums = BTreeMap::<_, f64>::new();
 
         for conn in connections {
             for (k, item) in &conn.items {
                 keys.insert(k);
                 if let Some(v) = item.value.as_f64() {
                     *sums.entry(k).or_default() += v;
+                    let sum = integer_sums.entry(k).or_insert(Some(0));
+                    *sum = sum.and_then(|sum| sum.checked_add(item.value.as_i128()?));
                 }
-                if let Some(delta) = item.delta_per_sec {
+                if let Some(delta) = item.delta {
                     *deltas.entry(k).or_default() += delta;
                 }
+                if let Some(delta) = item.delta_per_sec {
+                    *deltas_per_sec.entry(k).or_default() += delta;
+                }
+                if let Some(base) = item.base_value {
+                    *base_sums.entry(k).or_default() += base;
+                }
             }
         }
 
@@ -139,7 +396,10 @@ impl AggregatedStats {
             .map(|k| {
                 let v = AggregatedStatsItemValue {
                     value_sum: sums.get(k).copied(),
-                    delta_per_sec: deltas.get(k).copied(),
+                    integer_value_sum: integer_sums.get(k).copied().flatten(),
+                    delta: deltas.get(k).copied(),
+                    delta_per_sec: deltas_per_sec.get(k).copied(),
+                    base_value_sum: base_sums.get(k).copied(),
                 };
                 (k.to_owned(), v)
             })
@@ -151,6 +411,10 @@ impl AggregatedStats {
         self.items.get(key)
     }
 
+    pub fn iter(&self) -> impl '_ + Iterator<Item = (&StatsItemKey, &AggregatedStatsItemValue)> {
+        self.items.iter()
+    }
+
     pub fn filtered_items<'a>(
         &'a self,
         filter: &'a Regex,
@@ -165,20 +429,56 @@ pub struct Stats {
     pub timestamp: Duration,
     pub aggregated: AggregatedStats,
     pub connections: BTreeMap<ConnectionId, ConnectionStats>,
+
+    /// Connections hidden by the user (excluded from `connections` and `aggregated`).
+    pub hidden_connections: BTreeMap<ConnectionId, ConnectionStats>,
+
+    /// Whether the clock went backwards since the previous snapshot.
+    pub clock_skew: bool,
+
+    /// Number of the connections before being capped by `--max-connections` (if capped).
+    pub uncapped_connection_count: Option<usize>,
+
+    /// Number of the connections before being filtered by `--connection-filter` (if any were filtered out).
+    pub unfiltered_connection_count: Option<usize>,
+
+    /// Number of the connections whose `connection_id` duplicated another one in the snapshot.
+    pub duplicate_connection_count: usize,
+
+    /// Result of the HTTP request that fetched this snapshot (only available in polling mode).
+    pub request_status: Option<RequestStatus>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RequestStatus {
+    pub status: u16,
+    pub latency: Duration,
+
+    /// Actual interval since the previous successful request (`None` for the first one).
+    pub interval: Option<Duration>,
 }
 
 impl Stats {
+    /// Makes a snapshot from `connections`, which should be built by [`ConnectionStats::from_snapshot()`].
+    ///
+    /// Connection IDs are expected to be unique: if not, only the last connection with the same ID is kept.
     pub fn new(time: SystemTime, timestamp: Duration, connections: Vec<ConnectionStats>) -> Self {
-        let aggregated = AggregatedStats::new(&connections);
-        let connections = connections
+        let connections: BTreeMap<_, _> = connections
             .into_iter()
             .map(|c| (c.connection_id.clone(), c))
             .collect();
+        let aggregated = AggregatedStats::new(connections.values());
         Self {
             time,
             timestamp,
             aggregated,
             connections,
+            hidden_connections: BTreeMap::new(),
+            clock_skew: false,
+            uncapped_connection_count: None,
+            unfiltered_connection_count: None,
+            duplicate_connection_count: 0,
+            request_status: None,
         }
     }
 
@@ -188,18 +488,50 @@ impl Stats {
             timestamp: Duration::from_secs(0),
             aggregated: Default::default(),
             connections: Default::default(),
+            hidden_connections: Default::default(),
+            clock_skew: false,
+            uncapped_connection_count: None,
+            unfiltered_connection_count: None,
+            duplicate_connection_count: 0,
+            request_status: None,
         }
     }
 
+    /// Moves the connections in `hidden_ids` to `hidden_connections` (and the others back) and re-aggregates the stats.
+    pub fn set_hidden_connections(&mut self, hidden_ids: &BTreeSet<ConnectionId>) {
+        let mut connections = std::mem::take(&mut self.connections);
+        connections.append(&mut self.hidden_connections);
+        (self.hidden_connections, self.connections) = connections
+            .into_iter()
+            .partition(|(id, _)| hidden_ids.contains(id));
+        self.aggregated = AggregatedStats::new(self.connections.values());
+    }
+
     pub fn timestamp(&self) -> orfail::Result<Duration> {
         let t = self.time.elapsed().or_fail()?;
         Ok(t)
     }
 
+    /// Iterates over the aggregated stats items in the key order.
+    pub fn iter_aggregated(
+        &self,
+    ) -> impl '_ + Iterator<Item = (&StatsItemKey, &AggregatedStatsItemValue)> {
+        self.aggregated.iter()
+    }
+
+    /// Iterates over the connections in the connection ID order.
+    pub fn iter_connections(&self) -> impl '_ + Iterator<Item = &ConnectionStats> {
+        self.connections.values()
+    }
+
     pub fn connection_count(&self) -> usize {
         self.connections.len()
     }
 
+    pub fn item_count(&self) -> usize {
+        self.aggregated.items.len()
+    }
+
     pub fn filtered_item_count(&self, filter: &Regex) -> usize {
         self.aggregated
             .items
@@ -207,62 +539,176 @@ impl Stats {
             .filter(|(k, _)| filter.is_match(k))
             .count()
     }
+
+    /// Partitions the connections by the value of `group_key` and aggregates each partition.
+    ///
+    /// Connections that do not have `group_key` are put into the group named "".
+    pub fn groups(&self, group_key: &str) -> BTreeMap<String, StatsGroup> {
+        let mut partitions = BTreeMap::<_, Vec<_>>::new();
+        for conn in self.connections.values() {
+            let name = conn
+                .items
+                .get(group_key)
+                .map(|item| item.value.to_string())
+                .unwrap_or_default();
+            partitions.entry(name).or_default().push(conn);
+        }
+        partitions
+            .into_iter()
+            .map(|(name, connections)| {
+                let group = StatsGroup {
+                    connection_ids: connections
+                        .iter()
+                        .map(|c| c.connection_id.clone())
+                        .collect(),
+                    aggregated: AggregatedStats::new(connections),
+                };
+                (name, group)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StatsGroup {
+    pub connection_ids: BTreeSet<ConnectionId>,
+    pub aggregated: AggregatedStats,
 }
 
 #[derive(Debug, Clone)]
 pub struct ConnectionStats {
     pub connection_id: ConnectionId,
-    pub timestamp: chrono::DateTime<chrono::FixedOffset>,
+    pub timestamp: Timestamp,
     pub items: BTreeMap<StatsItemKey, ConnectionStatsItemValue>,
+
+    /// Original JSON object (used to write the stats back to a record file).
+    pub raw: serde_json::Value,
 }
 
 impl ConnectionStats {
-    pub fn new(json: serde_json::Value, prev: &Stats) -> orfail::Result<Self> {
+    /// Iterates over the stats items of this connection in the key order.
+    pub fn iter_items(
+        &self,
+    ) -> impl '_ + Iterator<Item = (&StatsItemKey, &ConnectionStatsItemValue)> {
+        self.items.iter()
+    }
+
+    /// Iterates over the `${KEY}:${VALUE}` strings that `--connection-filter` is matched against.
+    pub fn filter_targets(&self) -> impl '_ + Iterator<Item = String> {
+        self.items.iter().map(|(k, v)| format!("{}:{}", k, v.value))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ConnectionStatsItemValue> {
+        self.items.get(key)
+    }
+
+    /// `time` is used as the timestamp of the connection if the stats do not have the "timestamp" field.
+    pub fn new(json: serde_json::Value, prev: &Stats, time: SystemTime) -> orfail::Result<Self> {
+        Self::with_delta_window(json, std::slice::from_ref(prev), time)
+    }
+
+    /// Makes a connection stats whose delta per second is computed over `window`.
+    ///
+    /// `window` is ordered from oldest to newest and its last element is the previous snapshot.
+    /// The rate is the value change since the oldest snapshot that contains the connection divided by the elapsed time,
+    /// while `delta` is always the change since the previous snapshot.
+    pub fn with_delta_window(
+        json: serde_json::Value,
+        window: &[Stats],
+        time: SystemTime,
+    ) -> orfail::Result<Self> {
+        let connection_id = parse_connection_id(&json)?;
+        Self::with_connection_id(json, connection_id, window, time)
+    }
+
+    /// Makes the connection stats of a whole snapshot and returns them with the number of duplicate IDs.
+    ///
+    /// Duplicate connection IDs get a `#N` suffix (in order of appearance) before the delta is computed,
+    /// so that each `ID#N` is compared with the previous `ID#N`.
+    /// This is the supported way to build the connections passed to [`Stats::new()`].
+    pub fn from_snapshot(
+        values: Vec<serde_json::Value>,
+        window: &[Stats],
+        time: SystemTime,
+    ) -> orfail::Result<(Vec<Self>, usize)> {
+        let mut ids = BTreeSet::new();
+        let mut duplicate_count = 0;
+        let mut connections = Vec::with_capacity(values.len());
+        for json in values {
+            let mut connection_id = parse_connection_id(&json)?;
+            if ids.contains(&connection_id) {
+                let id = (2..)
+                    .map(|i| format!("{connection_id}#{i}"))
+                    .find(|id| !ids.contains(id))
+                    .expect("unreachable");
+                log::warn!(
+                    "Duplicate connection_id {connection_id:?} in a snapshot: shown as {id:?}"
+                );
+                connection_id = id;
+                duplicate_count += 1;
+            }
+            ids.insert(connection_id.clone());
+            connections.push(Self::with_connection_id(json, connection_id, window, time)?);
+        }
+        Ok((connections, duplicate_count))
+    }
+
+    fn with_connection_id(
+        json: serde_json::Value,
+        connection_id: ConnectionId,
+        window: &[Stats],
+        time: SystemTime,
+    ) -> orfail::Result<Self> {
         let obj = json
             .as_object()
             .or_fail_with(|_| "not a JSON object".to_owned())?;
-        let connection_id = obj
-            .get("connection_id")
-            .or_fail_with(|_| "missing 'connection_id'".to_owned())?
-            .as_str()
-            .or_fail_with(|_| "not a JSON string".to_owned())?
-            .to_owned();
-        let timestamp = obj
-            .get("timestamp")
-            .or_fail_with(|_| "missing 'timestamp'".to_owned())?
-            .as_str()
-            .or_fail_with(|_| "not a JSON string".to_owned())?
-            .to_owned();
-        let timestamp = chrono::DateTime::parse_from_rfc3339(&timestamp)
-            .or_fail_with(|e| format!("parse timestamp {timestamp:?} failed: {e}"))?;
+        let timestamp = if let Some(timestamp) = obj.get("timestamp") {
+            let timestamp = timestamp
+                .as_str()
+                .or_fail_with(|_| "not a JSON string".to_owned())?;
+            chrono::DateTime::parse_from_rfc3339(timestamp)
+                .or_fail_with(|e| format!("parse timestamp {timestamp:?} failed: {e}"))?
+        } else {
+            log::debug!(
+                "missing 'timestamp' (connection_id={connection_id}): use the poll time instead"
+            );
+            chrono::DateTime::<chrono::Utc>::from(time).fixed_offset()
+        };
 
         let mut key = String::new();
         let mut stats_items = BTreeMap::new();
         collect_stats_items(obj, &mut stats_items, &mut key);
 
-        let duration = prev
-            .connections
-            .get(&connection_id)
-            .map(|c| (timestamp - c.timestamp).to_std())
-            .transpose()
-            .or_fail()?;
+        let base = window
+            .iter()
+            .find_map(|s| s.connections.get(&connection_id));
+        let duration = base.and_then(|c| {
+            // Negative durations (i.e., clock skew) are treated as unknown.
+            (timestamp - c.timestamp).to_std().ok()
+        });
+        let prev_items = window
+            .last()
+            .and_then(|s| s.connections.get(&connection_id))
+            .map(|c| &c.items);
         let items = stats_items
             .into_iter()
             .map(|(k, v)| {
-                let delta_per_sec = if let Some(d) = duration {
-                    prev.connections[&connection_id]
-                        .items
-                        .get(&k)
-                        .and_then(|x| match (v.as_f64(), x.value.as_f64()) {
-                            (Some(v1), Some(v0)) => Some((v1 - v0) / d.as_secs_f64()),
-                            _ => None,
-                        })
-                } else {
-                    None
+                let prev_item = prev_items.and_then(|items| items.get(&k));
+                let delta = prev_item.and_then(|x| v.delta_from(&x.value));
+                let base_item = base.and_then(|c| c.items.get(&k));
+                let base_delta = base_item.and_then(|x| v.delta_from(&x.value));
+                let delta_per_sec = duration.and_then(|d| base_delta.map(|x| x / d.as_secs_f64()));
+                let base_value = delta_per_sec.and(base_item).and_then(|x| x.value.as_f64());
+                let transitions = match prev_item {
+                    Some(x) if v.as_f64().is_none() => x.transitions.next(&x.value, &v, timestamp),
+                    _ => ValueTransitions::default(),
                 };
                 let v = ConnectionStatsItemValue {
                     value: v,
+                    delta,
                     delta_per_sec,
+                    base_value,
+                    transitions,
                 };
                 (k, v)
             })
@@ -271,42 +717,206 @@ impl ConnectionStats {
             connection_id,
             timestamp,
             items,
+            raw: json,
         })
     }
 }
 
+fn parse_connection_id(json: &serde_json::Value) -> orfail::Result<ConnectionId> {
+    let connection_id = json
+        .as_object()
+        .or_fail_with(|_| "not a JSON object".to_owned())?
+        .get("connection_id")
+        .or_fail_with(|_| "missing 'connection_id'".to_owned())?
+        .as_str()
+        .or_fail_with(|_| "not a JSON string".to_owned())?;
+    Ok(connection_id.to_owned())
+}
+
+pub fn collect_stats_keys(values: &[serde_json::Value]) -> BTreeSet<StatsItemKey> {
+    let mut keys = BTreeSet::new();
+    for obj in values.iter().filter_map(|v| v.as_object()) {
+        let mut items = BTreeMap::new();
+        collect_stats_items(obj, &mut items, &mut String::new());
+        keys.extend(items.into_keys());
+    }
+    keys
+}
+
 fn collect_stats_items(
     obj: &serde_json::Map<String, serde_json::Value>,
     items: &mut BTreeMap<StatsItemKey, StatsItemValue>,
     key: &mut String,
 ) {
     for (k, v) in obj {
-        let old_len = key.len();
-        if !key.is_empty() {
-            key.push('.');
-        }
-        key.push_str(k);
-        match v {
-            serde_json::Value::Number(v) => {
-                if let Some(v) = v.as_f64() {
-                    items.insert(key.clone(), StatsItemValue::Number(v));
-                } else {
-                    log::warn!("too large number (ignored): {v}");
-                }
-            }
-            serde_json::Value::Bool(v) => {
-                items.insert(key.clone(), StatsItemValue::Bool(*v));
-            }
-            serde_json::Value::String(v) => {
-                items.insert(key.clone(), StatsItemValue::String(v.clone()));
-            }
-            serde_json::Value::Object(children) => {
-                collect_stats_items(children, items, key);
+        collect_stats_item(k, v, items, key);
+    }
+}
+
+fn collect_stats_item(
+    k: &str,
+    v: &serde_json::Value,
+    items: &mut BTreeMap<StatsItemKey, StatsItemValue>,
+    key: &mut String,
+) {
+    let old_len = key.len();
+    if !key.is_empty() {
+        key.push('.');
+    }
+    key.push_str(k);
+    match v {
+        serde_json::Value::Number(v) => {
+            if let Some(v) = v.as_u64() {
+                items.insert(key.clone(), StatsItemValue::UInt(v));
+            } else if let Some(v) = v.as_i64() {
+                items.insert(key.clone(), StatsItemValue::Int(v));
+            } else if let Some(v) = v.as_f64() {
+                items.insert(key.clone(), StatsItemValue::Number(v));
+            } else {
+                log::warn!("too large number (ignored): {v}");
             }
-            _ => {
-                log::warn!("unexpected stats value (ignored): {v}");
+        }
+        serde_json::Value::Bool(v) => {
+            items.insert(key.clone(), StatsItemValue::Bool(*v));
+        }
+        serde_json::Value::String(v) => {
+            items.insert(key.clone(), StatsItemValue::String(v.clone()));
+        }
+        serde_json::Value::Object(children) => {
+            collect_stats_items(children, items, key);
+        }
+        serde_json::Value::Array(elements) => {
+            // Elements are addressed by their indices (e.g., "codecs.0.mime_type").
+            for (i, v) in elements.iter().enumerate() {
+                collect_stats_item(&i.to_string(), v, items, key);
             }
+        }
+        serde_json::Value::Null => {
+            log::warn!("unexpected stats value (ignored): {v}");
+        }
+    };
+    key.truncate(old_len);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(rounding: RoundingMode) -> FormatContext {
+        FormatContext {
+            delta_precision: 0,
+            rounding,
+            unit: None,
+            compact_numbers: false,
+            percent_delta: false,
+        }
+    }
+
+    #[test]
+    fn flatten_nested_arrays() {
+        let json = serde_json::json!({
+            "codecs": [
+                {"mime_type": "audio/opus", "payload_type": 111},
+                {"mime_type": "video/VP8", "rtx": [96, 97]},
+            ],
+            "empty": [],
+        });
+        let mut items = BTreeMap::new();
+        collect_stats_items(json.as_object().unwrap(), &mut items, &mut String::new());
+        assert_eq!(
+            items.into_iter().collect::<Vec<_>>(),
+            [
+                (
+                    "codecs.0.mime_type".to_owned(),
+                    StatsItemValue::String("audio/opus".to_owned())
+                ),
+                (
+                    "codecs.0.payload_type".to_owned(),
+                    StatsItemValue::UInt(111)
+                ),
+                (
+                    "codecs.1.mime_type".to_owned(),
+                    StatsItemValue::String("video/VP8".to_owned())
+                ),
+                ("codecs.1.rtx.0".to_owned(), StatsItemValue::UInt(96)),
+                ("codecs.1.rtx.1".to_owned(), StatsItemValue::UInt(97)),
+            ]
+        );
+    }
+
+    #[test]
+    fn format_u64_groups_digits() {
+        assert_eq!(format_u64(0), "0");
+        assert_eq!(format_u64(999), "999");
+        assert_eq!(format_u64(1000), "1,000");
+        assert_eq!(format_u64(1_000_000), "1,000,000");
+        assert_eq!(format_u64(1_234_567), "1,234,567");
+        assert_eq!(format_u64(u64::MAX), "18,446,744,073,709,551,615");
+    }
+
+    #[test]
+    fn format_f64_rounds_half_up() {
+        let ctx = ctx(RoundingMode::HalfUp);
+        assert_eq!(ctx.format_f64(0.0, 0), "0");
+        assert_eq!(ctx.format_f64(0.4, 0), "0");
+        assert_eq!(ctx.format_f64(0.5, 0), "1");
+        assert_eq!(ctx.format_f64(998.5, 0), "999");
+        assert_eq!(ctx.format_f64(999.4, 0), "999");
+        assert_eq!(ctx.format_f64(999.5, 0), "1,000");
+        assert_eq!(ctx.format_f64(1_234_566.5, 0), "1,234,567");
+        assert_eq!(ctx.format_f64(-0.4, 0), "0");
+        assert_eq!(ctx.format_f64(-0.5, 0), "-1");
+        assert_eq!(ctx.format_f64(-999.5, 0), "-1,000");
+    }
+
+    #[test]
+    fn format_f64_rounds_half_even() {
+        let ctx = ctx(RoundingMode::HalfEven);
+        assert_eq!(ctx.format_f64(0.5, 0), "0");
+        assert_eq!(ctx.format_f64(1.5, 0), "2");
+        assert_eq!(ctx.format_f64(998.5, 0), "998");
+        assert_eq!(ctx.format_f64(999.5, 0), "1,000");
+        assert_eq!(ctx.format_f64(-0.5, 0), "0");
+    }
+
+    #[test]
+    fn format_f64_with_precision() {
+        let ctx = ctx(RoundingMode::HalfUp);
+        assert_eq!(ctx.format_f64(0.0, 2), "0.00");
+        assert_eq!(ctx.format_f64(0.125, 2), "0.13");
+        assert_eq!(ctx.format_f64(999.995, 2), "1,000.00");
+        assert_eq!(ctx.format_f64(1234.5, 1), "1,234.5");
+        assert_eq!(ctx.format_f64(-0.001, 2), "0.00");
+        assert_eq!(ctx.format_f64(-1234.56, 1), "-1,234.6");
+        assert_eq!(ctx.format_f64(0.05, 3), "0.050");
+    }
+
+    #[test]
+    fn unit_is_chosen_after_rounding() {
+        let ctx = FormatContext {
+            compact_numbers: true,
+            ..ctx(RoundingMode::HalfUp)
+        };
+        assert_eq!(ctx.format_number(999_950.0, 0), "1.0M");
+
+        let ctx = FormatContext {
+            unit: Some(Unit::Bytes),
+            ..ctx
+        };
+        assert_eq!(ctx.format_number(1_048_575.0, 0), "1.0 MiB");
+    }
+
+    #[test]
+    fn sum_and_delta_are_rounded_consistently() {
+        let ctx = ctx(RoundingMode::HalfUp);
+        let item = AggregatedStatsItemValue {
+            value_sum: Some(999.5),
+            integer_value_sum: None,
+            delta: Some(999.5),
+            delta_per_sec: Some(999.5),
+            base_value_sum: None,
         };
-        key.truncate(old_len);
+        assert_eq!(item.format_value_sum(ctx), "1,000");
+        assert_eq!(item.format_delta_per_sec(ctx), "1,000");
     }
 }
@@ -0,0 +1,167 @@
+use ratatui::style::{Color, Modifier, Style};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ThemeName {
+    Dark,
+    Light,
+    HighContrast,
+}
+
+/// Styles used throughout the UI (see `--theme`).
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub border: Style,
+    pub focused_border: Style,
+    pub title: Style,
+    pub header: Style,
+    pub highlight: Style,
+    pub status_bar: Style,
+    pub selected: Style,
+    pub pinned: Style,
+    pub dim: Style,
+    pub ok: Style,
+    pub warning: Style,
+    pub error: Style,
+    pub alert: Style,
+    pub scrubber: Style,
+    pub moving_average: Style,
+    pub annotation: Style,
+
+    /// Colors of the chart series (must not contain the colors of the moving average and annotations).
+    pub chart_palette: &'static [Color],
+}
+
+impl Theme {
+    pub fn new(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Dark => Self::dark(),
+            ThemeName::Light => Self::light(),
+            ThemeName::HighContrast => Self::high_contrast(),
+        }
+    }
+
+    fn dark() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let reversed = Style::default().add_modifier(Modifier::REVERSED);
+        Self {
+            border: Style::default(),
+            focused_border: bold,
+            title: bold,
+            header: bold,
+            highlight: reversed,
+            status_bar: reversed,
+            selected: Style::default().fg(Color::Cyan),
+            pinned: Style::default().fg(Color::Yellow),
+            dim: Style::default().add_modifier(Modifier::DIM),
+            ok: Style::default().fg(Color::Green),
+            warning: Style::default().fg(Color::Yellow),
+            error: Style::default().fg(Color::Red),
+            alert: Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            scrubber: Style::default().fg(Color::Cyan),
+            moving_average: Style::default().fg(Color::Magenta),
+            annotation: Style::default().fg(Color::Gray),
+            chart_palette: &[
+                Color::Cyan,
+                Color::Green,
+                Color::Yellow,
+                Color::Blue,
+                Color::LightRed,
+                Color::LightGreen,
+                Color::LightBlue,
+                Color::LightYellow,
+            ],
+        }
+    }
+
+    // Avoids yellow and light colors, which are barely visible on a white background.
+    fn light() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Self {
+            border: Style::default().fg(Color::DarkGray),
+            focused_border: Style::default()
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            title: bold,
+            header: Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+            highlight: Style::default().bg(Color::Indexed(153)).fg(Color::Black),
+            status_bar: Style::default().bg(Color::Indexed(252)).fg(Color::Black),
+            selected: Style::default().fg(Color::Blue),
+            pinned: Style::default().fg(Color::Indexed(130)),
+            dim: Style::default().fg(Color::DarkGray),
+            ok: Style::default().fg(Color::Indexed(28)),
+            warning: Style::default().fg(Color::Indexed(130)),
+            error: Style::default().fg(Color::Red),
+            alert: Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            scrubber: Style::default().fg(Color::Blue),
+            moving_average: Style::default().fg(Color::Magenta),
+            annotation: Style::default().fg(Color::DarkGray),
+            chart_palette: &[
+                Color::Blue,
+                Color::Indexed(28),
+                Color::Red,
+                Color::Indexed(130),
+                Color::Indexed(30),
+                Color::Indexed(55),
+                Color::Indexed(94),
+                Color::Indexed(24),
+            ],
+        }
+    }
+
+    // Uses only bright colors and explicit backgrounds instead of modifiers that some terminals render faintly.
+    fn high_contrast() -> Self {
+        let bold_white = Style::default()
+            .fg(Color::White)
+            .add_modifier(Modifier::BOLD);
+        Self {
+            border: Style::default().fg(Color::White),
+            focused_border: Style::default()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+            title: bold_white,
+            header: bold_white.add_modifier(Modifier::UNDERLINED),
+            highlight: Style::default()
+                .bg(Color::LightYellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            status_bar: Style::default().bg(Color::White).fg(Color::Black),
+            selected: Style::default()
+                .fg(Color::LightCyan)
+                .add_modifier(Modifier::BOLD),
+            pinned: Style::default()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+            dim: Style::default().fg(Color::Gray),
+            ok: Style::default()
+                .fg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD),
+            warning: Style::default()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+            error: Style::default()
+                .fg(Color::LightRed)
+                .add_modifier(Modifier::BOLD),
+            alert: Style::default()
+                .bg(Color::Red)
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            scrubber: Style::default().fg(Color::LightCyan),
+            moving_average: Style::default().fg(Color::LightMagenta),
+            annotation: Style::default().fg(Color::White),
+            chart_palette: &[
+                Color::LightCyan,
+                Color::LightGreen,
+                Color::LightYellow,
+                Color::LightRed,
+                Color::LightBlue,
+            ],
+        }
+    }
+
+    /// Returns the color of the series of the given key (the same key always gets the same color).
+    pub fn color_for_key(&self, key: &str) -> Color {
+        self.chart_palette[crate::fnv1a_hash(key) as usize % self.chart_palette.len()]
+    }
+}
@@ -1,20 +1,34 @@
+use crate::alert::Alert;
 use crate::poll::StatsReceiver;
-use crate::stats::{format_u64, Stats};
-use crate::Options;
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use crate::record::{RecordItem, RecordMetadata, Recorder};
+use crate::stats::{
+    format_u64, AggregatedStats, AggregatedStatsItemValue, ConnectionId, ConnectionStats,
+    FormatContext, Stats, StatsItemKey, StatsItemValue,
+};
+use crate::theme::Theme;
+use crate::{ChartType, ColumnLayout, Options, TimeDisplay};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind};
 use orfail::OrFail;
-use ratatui::layout::{Alignment, Constraint, Direction, Layout};
-use ratatui::style::{Color, Modifier, Style};
+use ratatui::layout::{Alignment, Constraint, Direction, Layout, Position, Rect};
+use ratatui::style::{Modifier, Style};
 use ratatui::symbols::Marker;
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{
-    Axis, Block, Borders, Cell, Chart, Dataset, GraphType, Paragraph, Row, Table, TableState,
+    Axis, Bar, BarChart, BarGroup, Block, Borders, Cell, Chart, Clear, Dataset, GraphType,
+    Paragraph, Row, Table, TableState, Wrap,
 };
 use ratatui::Frame;
 use regex::Regex;
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::io::Write;
 use std::time::{Duration, Instant, SystemTime};
 
+// Resize events are coalesced so that dragging the window edge does not redraw the screen too often.
+const RESIZE_REDRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+// Number of the help lines shown without scrolling.
+const HELP_MIN_LINES: usize = 3;
+
 type Terminal = ratatui::Terminal<ratatui::backend::CrosstermBackend<std::io::Stdout>>;
 
 pub struct App {
@@ -22,21 +36,29 @@ pub struct App {
     terminal: Terminal,
     ui: UiState,
     start_time: Instant,
+    poll_count: usize,
+    resize_pending: bool,
+    last_resize_redraw: Instant,
 }
 
 impl App {
     pub fn new(rx: StatsReceiver, options: Options) -> orfail::Result<Self> {
-        let terminal = Self::setup_terminal().or_fail()?;
+        let terminal = Self::setup_terminal(&options).or_fail()?;
         std::panic::set_hook(Box::new(|info| {
             log::error!("{info}");
         }));
         log::debug!("setup terminal");
-        let ui = UiState::new(options);
+        let mut ui = UiState::new(options);
+        ui.schema_warning = rx.schema_warning.clone();
+        ui.record_metadata = rx.metadata.clone();
         Ok(Self {
             rx,
             ui,
             terminal,
             start_time: Instant::now(),
+            poll_count: 0,
+            resize_pending: false,
+            last_resize_redraw: Instant::now(),
         })
     }
 
@@ -50,16 +72,32 @@ impl App {
                 break;
             }
             if self.ui.realtime {
+                if self.is_run_limit_reached() {
+                    log::info!("exit as the --run-for / --max-polls limit has been reached");
+                    break;
+                }
                 if self.ui.pause {
                     std::thread::sleep(self.recv_timeout());
                 } else {
                     self.handle_realtime_stats_poll().or_fail()?;
                 }
+            } else if self.ui.options.follow {
+                self.handle_follow_stats_poll().or_fail()?;
             }
         }
         Ok(())
     }
 
+    fn is_run_limit_reached(&self) -> bool {
+        let options = &self.ui.options;
+        options
+            .run_for
+            .is_some_and(|d| self.start_time.elapsed() >= d)
+            || options
+                .max_polls
+                .is_some_and(|n| self.poll_count >= n.get())
+    }
+
     fn recv_timeout(&self) -> Duration {
         Duration::from_millis(10)
     }
@@ -107,7 +145,9 @@ impl App {
             if consumed {
                 if let Ok(regex) = Regex::new(&editing.text) {
                     editing.valid = true;
-                    self.ui.options.stats_key_filter = regex;
+                    self.ui.options.stats_key_filter = regex.clone();
+                    self.ui.default_stats_key_filter = regex;
+                    self.ui.filter_preset = None;
                 } else {
                     editing.valid = false;
                 }
@@ -122,13 +162,98 @@ impl App {
             }
         }
 
+        if let Some(pattern) = &mut self.ui.testing_connection_filter {
+            if key.kind != KeyEventKind::Press {
+                return Ok(false);
+            }
+            match key.code {
+                KeyCode::Char(c) => pattern.push(c),
+                KeyCode::Backspace => {
+                    pattern.pop();
+                }
+                KeyCode::Enter | KeyCode::Esc => {
+                    self.ui.testing_connection_filter = None;
+                }
+                _ => return Ok(false),
+            }
+            self.terminal.draw(|f| self.ui.render(f)).or_fail()?;
+            return Ok(false);
+        }
+
+        if let Some(search) = &mut self.ui.key_search {
+            if key.kind != KeyEventKind::Press {
+                return Ok(false);
+            }
+            match key.code {
+                KeyCode::Char(c) => search.query.push(c),
+                KeyCode::Backspace => {
+                    search.query.pop();
+                }
+                KeyCode::Tab => search.fuzzy = !search.fuzzy,
+                KeyCode::Enter => {
+                    self.ui.key_search = None;
+                }
+                KeyCode::Esc => {
+                    let search = self.ui.key_search.take().expect("unreachable");
+                    self.ui.focus = search.origin_focus;
+                    self.ui
+                        .aggregated_table_state
+                        .select(search.origin_selected);
+                }
+                _ => return Ok(false),
+            }
+            self.ui.jump_to_key_search_match();
+            self.terminal.draw(|f| self.ui.render(f)).or_fail()?;
+            return Ok(false);
+        }
+
+        if let Some(label) = &mut self.ui.editing_annotation {
+            if key.kind != KeyEventKind::Press {
+                return Ok(false);
+            }
+            match key.code {
+                KeyCode::Char(c) => label.push(c),
+                KeyCode::Backspace => {
+                    label.pop();
+                }
+                KeyCode::Enter => {
+                    let label = std::mem::take(label);
+                    self.ui.editing_annotation = None;
+                    self.ui.add_annotation(label);
+                }
+                KeyCode::Esc => {
+                    self.ui.editing_annotation = None;
+                }
+                _ => return Ok(false),
+            }
+            self.terminal.draw(|f| self.ui.render(f)).or_fail()?;
+            return Ok(false);
+        }
+
+        if self.ui.confirming_quit {
+            if key.kind != KeyEventKind::Press {
+                return Ok(false);
+            }
+            if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                return Ok(true);
+            }
+            self.ui.confirming_quit = false;
+            self.terminal.draw(|f| self.ui.render(f)).or_fail()?;
+            return Ok(false);
+        }
+
+        self.ui.notice = None;
         match key.code {
             KeyCode::Char('q') => {
-                return Ok(true);
+                if !self.ui.is_recording() {
+                    return Ok(true);
+                }
+                self.ui.confirming_quit = true;
             }
             KeyCode::Char('p') => {
                 if self.ui.realtime {
                     self.ui.pause = !self.ui.pause;
+                    self.ui.paused_on_alert = false;
                 }
             }
             KeyCode::Char('l') => {
@@ -137,35 +262,220 @@ impl App {
                 }
             }
             KeyCode::Char('h') => {
-                self.ui.end_pos = std::cmp::max(1, self.ui.end_pos.saturating_sub(1));
+                if !self.ui.realtime {
+                    self.ui.end_pos = std::cmp::max(1, self.ui.end_pos.saturating_sub(1));
+                    self.ui.update_totals();
+                    self.ui.ensure_table_indices_are_in_ranges();
+                }
+            }
+            KeyCode::Home | KeyCode::Char('0') => {
+                if !self.ui.realtime {
+                    self.ui.end_pos = 1;
+                    self.ui.update_totals();
+                    self.ui.ensure_table_indices_are_in_ranges();
+                }
+            }
+            KeyCode::End | KeyCode::Char('$') => {
+                if !self.ui.realtime {
+                    self.ui.end_pos = self.ui.history.len();
+                    self.ui.update_totals();
+                    self.ui.ensure_table_indices_are_in_ranges();
+                }
+            }
+            KeyCode::Char('t') => {
+                self.ui.show_totals = !self.ui.show_totals;
+            }
+            KeyCode::Char('R') => {
+                self.ui.reset_totals();
+            }
+            KeyCode::Char('m') => {
+                self.ui.show_moving_average = !self.ui.show_moving_average;
+            }
+            KeyCode::Char('r') => {
+                self.ui.show_ratio = !self.ui.show_ratio;
+            }
+            KeyCode::Char('L') => {
+                self.ui.toggle_chart_lock();
+            }
+            KeyCode::Char('k') => {
+                self.ui.options.compact_numbers = !self.ui.options.compact_numbers;
+            }
+            KeyCode::Char('%') => {
+                self.ui.options.percent_delta = !self.ui.options.percent_delta;
+            }
+            KeyCode::Char('U') => {
+                self.ui.options.utc = !self.ui.options.utc;
+            }
+            KeyCode::Char('u') => {
+                if self.ui.realtime && !self.ui.options.stream {
+                    self.rx.poll_now();
+                    self.ui.notice = Some("Refreshing stats now...".to_owned());
+                }
+            }
+            KeyCode::Char('e') => {
+                self.ui.notice = Some(match self.ui.export_chart_data() {
+                    Ok(message) => message,
+                    Err(e) => {
+                        log::warn!("failed to export chart data: {e}");
+                        format!("[ERROR] Failed to export chart data: {}", e.message)
+                    }
+                });
+            }
+            KeyCode::Char('J') => {
+                self.ui.notice = Some(match self.ui.export_chart_json() {
+                    Ok(message) => message,
+                    Err(e) => {
+                        log::warn!("failed to export chart series: {e}");
+                        format!("[ERROR] Failed to export chart series: {}", e.message)
+                    }
+                });
+            }
+            KeyCode::Char('w') => {
+                self.ui.notice = Some(match self.ui.export_history() {
+                    Ok(message) => message,
+                    Err(e) => {
+                        log::warn!("failed to export history: {e}");
+                        format!("[ERROR] Failed to export history: {}", e.message)
+                    }
+                });
+            }
+            KeyCode::Char('T') => {
+                self.ui.testing_connection_filter =
+                    Some(self.ui.options.connection_filter.to_string());
+            }
+            KeyCode::Char('A') => {
+                self.ui.editing_annotation = Some(String::new());
+            }
+            KeyCode::Char('f') => {
+                self.ui.key_search = Some(KeySearch {
+                    query: String::new(),
+                    fuzzy: false,
+                    match_count: 0,
+                    origin_focus: self.ui.focus,
+                    origin_selected: self.ui.aggregated_table_state.selected(),
+                });
+                self.ui.jump_to_key_search_match();
+            }
+            KeyCode::Char('s') => {
+                self.ui.natural_sort = !self.ui.natural_sort;
+            }
+            KeyCode::Char('H') => {
+                self.ui.toggle_hidden_connection();
+                self.ui.ensure_table_indices_are_in_ranges();
+            }
+            KeyCode::Char('S') => {
+                self.ui.split_by_media = !self.ui.split_by_media;
+            }
+            KeyCode::Char('C') => {
+                self.ui.show_unfiltered_count = !self.ui.show_unfiltered_count;
+            }
+            KeyCode::Char('?') => {
+                // Wrapped around to the first page while rendering.
+                self.ui.help_page += 1;
+            }
+            KeyCode::Char('n') => {
+                self.ui.show_average = !self.ui.show_average;
+            }
+            KeyCode::Char('b') => {
+                self.ui.show_histogram = !self.ui.show_histogram;
+            }
+            KeyCode::Char('i') => {
+                self.ui.show_delta_detail = !self.ui.show_delta_detail;
+            }
+            KeyCode::Char('c') => {
+                self.ui.show_value_types = !self.ui.show_value_types;
+            }
+            KeyCode::Char('d') => {
+                self.ui.toggle_connection_detail();
+            }
+            KeyCode::Char('z') | KeyCode::Enter => {
+                self.ui.zoom = !self.ui.zoom;
+            }
+            KeyCode::Tab => {
+                self.ui.cycle_filter_preset();
             }
             KeyCode::Char('/') => {
                 self.ui.editing_stats_key_filter =
                     Some(EditingStatsKeyFilter::new(&self.ui.options));
             }
-            KeyCode::Left => {
-                self.ui.focus = Focus::AggregatedStats;
+            KeyCode::Char('P') => {
+                if let Some(key) = self.ui.selected_item_key().map(|k| k.to_owned()) {
+                    if !self.ui.pinned_keys.remove(&key) {
+                        self.ui.pinned_keys.insert(key.clone());
+                    }
+                    self.ui.select_item_key(&key);
+                }
             }
-            KeyCode::Right => {
-                self.ui.focus = Focus::IndividualStats;
+            KeyCode::Char(' ') => {
+                if let Some(key) = self.ui.selected_item_key().map(|k| k.to_owned()) {
+                    if let Some(i) = self.ui.selected_keys.iter().position(|k| *k == key) {
+                        self.ui.selected_keys.remove(i);
+                    } else {
+                        self.ui.selected_keys.push(key);
+                    }
+                }
             }
-            KeyCode::Up => {
-                let table = if self.ui.focus == Focus::AggregatedStats {
-                    &mut self.ui.aggregated_table_state
-                } else {
-                    &mut self.ui.individual_table_state
+            KeyCode::Esc if self.ui.connection_detail.is_some() => {
+                self.ui.connection_detail = None;
+            }
+            KeyCode::Char('a') => {
+                self.ui.toggle_alert_log();
+            }
+            KeyCode::Esc if self.ui.alert_log_table_state.is_some() => {
+                self.ui.alert_log_table_state = None;
+            }
+            KeyCode::Char('M') => {
+                self.ui.select_max_delta_row();
+            }
+            KeyCode::Char('x') => {
+                self.ui.toggle_compare_key();
+            }
+            KeyCode::Esc => {
+                self.ui.selected_keys.clear();
+                self.ui.compare_keys.clear();
+                self.ui.individual_hscroll = 0;
+            }
+            KeyCode::Char('[') => {
+                self.ui.individual_hscroll = self.ui.individual_hscroll.saturating_sub(1);
+            }
+            KeyCode::Char(']') => {
+                let n = self.ui.visible_selected_keys().len();
+                self.ui.individual_hscroll =
+                    std::cmp::min(self.ui.individual_hscroll + 1, n.saturating_sub(1));
+            }
+            KeyCode::Left | KeyCode::Right => {
+                // The keys move the focus in the direction on the screen, which depends on `--layout`.
+                let details_left = self.ui.options.layout == ColumnLayout::DetailsLeft;
+                let toward_details = (key.code == KeyCode::Right) != details_left;
+                self.ui.focus = match self.ui.focus {
+                    Focus::IndividualStats
+                        if !toward_details && self.ui.options.group_by.is_some() =>
+                    {
+                        Focus::Groups
+                    }
+                    Focus::AggregatedStats
+                        if toward_details && self.ui.options.group_by.is_some() =>
+                    {
+                        Focus::Groups
+                    }
+                    _ if toward_details => Focus::IndividualStats,
+                    _ => Focus::AggregatedStats,
                 };
-
+            }
+            KeyCode::Char('o') => {
+                self.ui.options.layout = match self.ui.options.layout {
+                    ColumnLayout::AggregatedLeft => ColumnLayout::DetailsLeft,
+                    ColumnLayout::DetailsLeft => ColumnLayout::AggregatedLeft,
+                };
+            }
+            KeyCode::Up => {
+                let table = self.ui.focused_table_state();
                 let i = table.selected().unwrap_or(0).saturating_sub(1);
                 table.select(Some(i));
                 self.ui.ensure_table_indices_are_in_ranges();
             }
             KeyCode::Down => {
-                let table = if self.ui.focus == Focus::AggregatedStats {
-                    &mut self.ui.aggregated_table_state
-                } else {
-                    &mut self.ui.individual_table_state
-                };
+                let table = self.ui.focused_table_state();
 
                 let i = table.selected().unwrap_or(0) + 1;
                 table.select(Some(i));
@@ -183,30 +493,55 @@ impl App {
         while crossterm::event::poll(std::time::Duration::from_secs(0)).or_fail()? {
             match crossterm::event::read().or_fail()? {
                 crossterm::event::Event::Key(key) => {
-                    if self.handle_key_event(key)? {
+                    let quit = self.handle_key_event(key)?;
+                    if quit {
                         return Ok(true);
                     }
                 }
+                crossterm::event::Event::Mouse(mouse) => {
+                    self.handle_mouse_event(mouse)?;
+                }
                 crossterm::event::Event::Resize(_, _) => {
-                    self.terminal.draw(|f| self.ui.render(f)).or_fail()?;
+                    self.resize_pending = true;
                 }
                 _ => {}
             }
         }
+        if self.resize_pending && self.last_resize_redraw.elapsed() >= RESIZE_REDRAW_INTERVAL {
+            self.resize_pending = false;
+            self.last_resize_redraw = Instant::now();
+            self.terminal.draw(|f| self.ui.render(f)).or_fail()?;
+        }
         Ok(false)
     }
 
+    fn handle_mouse_event(&mut self, mouse: MouseEvent) -> orfail::Result<()> {
+        if mouse.kind != MouseEventKind::Down(MouseButton::Left) {
+            return Ok(());
+        }
+        if self.ui.handle_click(Position::new(mouse.column, mouse.row)) {
+            self.terminal.draw(|f| self.ui.render(f)).or_fail()?;
+        }
+        Ok(())
+    }
+
     fn handle_replay_stats_poll(&mut self) -> orfail::Result<()> {
         if self.ui.end_pos < self.ui.history.len() {
             self.ui.end_pos += 1;
+        } else if self.ui.options.follow && !self.ui.history.is_empty() {
+            // New records are received by `handle_follow_stats_poll()`.
         } else if let Ok(stats) = self.rx.recv() {
-            let stats = stats.or_fail()?;
+            let stats = stats.map_err(orfail::Failure::new)?;
             log::debug!("recv new stats");
-            self.ui.history.push_back(stats);
+            self.ui.push_history(stats);
             self.ui.end_pos += 1;
         } else {
             self.ui.eof = true;
+            if self.ui.history.is_empty() {
+                return Err(orfail::Failure::new("no stats found in the record file"));
+            }
         }
+        self.ui.update_totals();
 
         self.ui.ensure_table_indices_are_in_ranges();
         self.terminal.draw(|f| self.ui.render(f)).or_fail()?;
@@ -214,6 +549,31 @@ impl App {
         Ok(())
     }
 
+    fn handle_follow_stats_poll(&mut self) -> orfail::Result<()> {
+        match self.rx.recv_timeout(self.recv_timeout()) {
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                if !self.ui.eof {
+                    self.ui.eof = true;
+                    self.terminal.draw(|f| self.ui.render(f)).or_fail()?;
+                }
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+            Ok(stats) => {
+                let stats = stats.map_err(orfail::Failure::new)?;
+                log::debug!("recv new stats");
+                let is_tail = self.ui.end_pos == self.ui.history.len();
+                self.ui.push_history(stats);
+                if is_tail {
+                    self.ui.end_pos += 1;
+                    self.ui.update_totals();
+                }
+                self.ui.ensure_table_indices_are_in_ranges();
+                self.terminal.draw(|f| self.ui.render(f)).or_fail()?;
+            }
+        }
+        Ok(())
+    }
+
     fn handle_realtime_stats_poll(&mut self) -> orfail::Result<()> {
         match self.rx.recv_timeout(self.recv_timeout()) {
             Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
@@ -223,15 +583,32 @@ impl App {
             }
             Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
             Ok(stats) => {
+                self.poll_count += 1;
                 let timestamp = self.start_time.elapsed();
-                if let Some(mut stats) = stats {
-                    log::debug!("recv new stats");
-                    self.ui.poll_failed_count = 0;
-                    stats.timestamp = timestamp;
-                    self.ui.history.push_back(stats);
-                } else {
-                    self.ui.poll_failed_count += 1;
-                };
+                match stats {
+                    Ok(mut stats) => {
+                        log::debug!("recv new stats");
+                        self.ui.poll_failed_count = 0;
+                        self.ui.last_poll_error = None;
+                        stats.timestamp = timestamp;
+                        self.ui.update_connection_churn(&stats);
+                        let prev_alerts = self.ui.active_alert_ids();
+                        self.ui.push_history(stats);
+                        self.ui.update_totals();
+                        self.ui.log_new_alerts(&prev_alerts);
+                        if self.ui.options.pause_on_alert
+                            && !self.ui.active_alert_ids().is_subset(&prev_alerts)
+                        {
+                            log::info!("pause on alert");
+                            self.ui.pause = true;
+                            self.ui.paused_on_alert = true;
+                        }
+                    }
+                    Err(e) => {
+                        self.ui.poll_failed_count += 1;
+                        self.ui.last_poll_error = Some(e);
+                    }
+                }
                 while let Some(item) = self.ui.history.pop_front() {
                     let duration = (timestamp.checked_sub(item.timestamp)).or_fail()?.as_secs();
                     if duration <= self.ui.options.chart_time_period.get() as u64 {
@@ -247,10 +624,15 @@ impl App {
         Ok(())
     }
 
-    fn setup_terminal() -> orfail::Result<Terminal> {
+    fn setup_terminal(options: &Options) -> orfail::Result<Terminal> {
         crossterm::terminal::enable_raw_mode().or_fail()?;
         let mut stdout = std::io::stdout();
-        crossterm::execute!(stdout, crossterm::terminal::EnterAlternateScreen).or_fail()?;
+        if !options.no_altscreen {
+            crossterm::execute!(stdout, crossterm::terminal::EnterAlternateScreen).or_fail()?;
+        }
+        if options.mouse {
+            crossterm::execute!(stdout, crossterm::event::EnableMouseCapture).or_fail()?;
+        }
         let backend = ratatui::backend::CrosstermBackend::new(stdout);
         let terminal = ratatui::Terminal::new(backend).or_fail()?;
         Ok(terminal)
@@ -258,11 +640,29 @@ impl App {
 
     fn teardown_terminal(&mut self) -> orfail::Result<()> {
         crossterm::terminal::disable_raw_mode().or_fail()?;
-        crossterm::execute!(
-            self.terminal.backend_mut(),
-            crossterm::terminal::LeaveAlternateScreen,
-        )
-        .or_fail()?;
+        if self.ui.options.mouse {
+            crossterm::execute!(
+                self.terminal.backend_mut(),
+                crossterm::event::DisableMouseCapture
+            )
+            .or_fail()?;
+        }
+        if self.ui.options.no_altscreen {
+            // Leave the last rendered screen as is and move the cursor below it.
+            let height = self.terminal.size().or_fail()?.height;
+            crossterm::execute!(
+                self.terminal.backend_mut(),
+                crossterm::cursor::MoveTo(0, height.saturating_sub(1)),
+                crossterm::style::Print("\r\n"),
+            )
+            .or_fail()?;
+        } else {
+            crossterm::execute!(
+                self.terminal.backend_mut(),
+                crossterm::terminal::LeaveAlternateScreen,
+            )
+            .or_fail()?;
+        }
         self.terminal.show_cursor().or_fail()?;
         Ok(())
     }
@@ -281,23 +681,181 @@ impl Drop for App {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Focus {
     AggregatedStats,
+    Groups,
     IndividualStats,
 }
 
+/// When a key binding is listed in the help pane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyContext {
+    Always,
+    Realtime,
+    Replay,
+    Focus(Focus),
+}
+
+#[derive(Debug)]
+struct KeyBinding {
+    keys: &'static str,
+    description: &'static str,
+    context: KeyContext,
+}
+
+impl KeyBinding {
+    const fn new(keys: &'static str, description: &'static str, context: KeyContext) -> Self {
+        Self {
+            keys,
+            description,
+            context,
+        }
+    }
+}
+
+/// Key bindings listed in the help pane (in the listed order).
+const KEY_BINDINGS: &[KeyBinding] = &[
+    KeyBinding::new("'q'", "Quit", KeyContext::Always),
+    KeyBinding::new("'p'", "Pause / Resume", KeyContext::Realtime),
+    KeyBinding::new("'u'", "Refresh now", KeyContext::Realtime),
+    KeyBinding::new("'h' / 'l'", "Prev / Next", KeyContext::Replay),
+    KeyBinding::new("'0' / '$'", "First / Last", KeyContext::Replay),
+    KeyBinding::new("UP / DOWN / LEFT / RIGHT", "Move", KeyContext::Always),
+    KeyBinding::new("'M'", "Largest Delta/s", KeyContext::Always),
+    KeyBinding::new("'/'", "Edit key filter", KeyContext::Always),
+    KeyBinding::new("'f'", "Search key", KeyContext::Always),
+    KeyBinding::new("Tab", "Filter presets", KeyContext::Always),
+    KeyBinding::new("'P'", "Pin key", KeyContext::Focus(Focus::AggregatedStats)),
+    KeyBinding::new(
+        "Space",
+        "Select key",
+        KeyContext::Focus(Focus::AggregatedStats),
+    ),
+    KeyBinding::new(
+        "'x'",
+        "Compare key",
+        KeyContext::Focus(Focus::AggregatedStats),
+    ),
+    KeyBinding::new(
+        "'S'",
+        "Split by media",
+        KeyContext::Focus(Focus::AggregatedStats),
+    ),
+    KeyBinding::new(
+        "'d'",
+        "Connection detail",
+        KeyContext::Focus(Focus::IndividualStats),
+    ),
+    KeyBinding::new(
+        "'H'",
+        "Hide connection",
+        KeyContext::Focus(Focus::IndividualStats),
+    ),
+    KeyBinding::new(
+        "'H'",
+        "Unhide connections",
+        KeyContext::Focus(Focus::AggregatedStats),
+    ),
+    KeyBinding::new(
+        "'[' / ']'",
+        "Scroll columns",
+        KeyContext::Focus(Focus::IndividualStats),
+    ),
+    KeyBinding::new(
+        "'s'",
+        "Natural sort",
+        KeyContext::Focus(Focus::IndividualStats),
+    ),
+    KeyBinding::new("'n'", "Sum / Average", KeyContext::Always),
+    KeyBinding::new("Esc", "Clear selection", KeyContext::Always),
+    KeyBinding::new("'z' / Enter", "Zoom chart", KeyContext::Always),
+    KeyBinding::new("'L'", "Lock chart", KeyContext::Always),
+    KeyBinding::new("'m'", "Moving average", KeyContext::Always),
+    KeyBinding::new("'r'", "Ratio chart", KeyContext::Always),
+    KeyBinding::new("'b'", "Histogram", KeyContext::Always),
+    KeyBinding::new("'i'", "Delta detail", KeyContext::Always),
+    KeyBinding::new("'c'", "Value types", KeyContext::Always),
+    KeyBinding::new("'t' / 'R'", "Totals / Reset", KeyContext::Always),
+    KeyBinding::new("'k'", "Compact numbers", KeyContext::Always),
+    KeyBinding::new("'%'", "Percent delta", KeyContext::Always),
+    KeyBinding::new("'U'", "UTC time", KeyContext::Always),
+    KeyBinding::new("'C'", "Unfiltered count", KeyContext::Always),
+    KeyBinding::new("'T'", "Test conn filter", KeyContext::Always),
+    KeyBinding::new("'A'", "Annotate", KeyContext::Always),
+    KeyBinding::new("'a'", "Alert log", KeyContext::Realtime),
+    KeyBinding::new("'e'", "Export chart CSV", KeyContext::Always),
+    KeyBinding::new("'J'", "Export chart JSON", KeyContext::Always),
+    KeyBinding::new("'w'", "Export history", KeyContext::Always),
+    KeyBinding::new("'o'", "Swap columns", KeyContext::Always),
+    KeyBinding::new("'?'", "Scroll help", KeyContext::Always),
+];
+
+/// Region of the last rendered frame that reacts to mouse clicks (`--mouse`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClickTarget {
+    Table(Focus),
+    Scrubber,
+}
+
 #[derive(Debug)]
 struct UiState {
     options: Options,
+    theme: Theme,
     history: VecDeque<Stats>,
     aggregated_table_state: TableState,
     individual_table_state: TableState,
+    groups_table_state: TableState,
     focus: Focus,
+    click_targets: Vec<(ClickTarget, Rect)>,
+
+    // Index of the row with the largest delta per second in each table (cached while rendering).
+    max_delta_rows: Vec<(Focus, usize)>,
+
     pause: bool,
+    paused_on_alert: bool,
+
+    // Newly fired alerts (oldest first) with the timestamps of the snapshots.
+    alert_log: VecDeque<(Duration, Alert)>,
+
+    // `Some` while the alert log is shown.
+    alert_log_table_state: Option<TableState>,
     realtime: bool,
     poll_failed_count: usize,
+    last_poll_error: Option<String>,
     editing_stats_key_filter: Option<EditingStatsKeyFilter>,
+    pinned_keys: BTreeSet<StatsItemKey>,
+    split_by_media: bool,
+    media_sections: Vec<(String, Regex)>,
+    selected_keys: Vec<StatsItemKey>,
+    compare_keys: Vec<StatsItemKey>,
+    confirming_quit: bool,
+    show_totals: bool,
+    totals: SessionTotals,
+    show_moving_average: bool,
+    show_ratio: bool,
+    zoom: bool,
+    notice: Option<String>,
+    show_delta_detail: bool,
+    show_value_types: bool,
+    connection_detail: Option<ConnectionDetail>,
+    chart_lock: Option<ChartLock>,
+    show_histogram: bool,
+    natural_sort: bool,
+    show_unfiltered_count: bool,
+    hidden_connections: BTreeSet<ConnectionId>,
+    show_average: bool,
+    help_page: usize,
+    individual_hscroll: usize,
+    connection_churn: Option<ConnectionChurn>,
+    annotations: Vec<Annotation>,
+    editing_annotation: Option<String>,
+    key_search: Option<KeySearch>,
+    testing_connection_filter: Option<String>,
+    filter_preset: Option<usize>,
+    default_stats_key_filter: Regex,
 
     // For replay mode
     eof: bool,
+    schema_warning: Option<String>,
+    record_metadata: Option<RecordMetadata>,
     end_pos: usize,
 }
 
@@ -312,21 +870,259 @@ impl UiState {
                 Vec::new(),
             ));
         }
+        let pinned_keys = options.pinned_keys.iter().cloned().collect();
+        let split_by_media = options.split_by_media;
+        let media_sections = if options.media_section.is_empty() {
+            ["audio", "video"]
+                .into_iter()
+                .map(|name| {
+                    let regex = Regex::new(&format!("(?i){name}")).expect("unreachable");
+                    (name.to_owned(), regex)
+                })
+                .collect()
+        } else {
+            options.media_section.clone()
+        };
+        let default_stats_key_filter = options.stats_key_filter.clone();
+        let theme = Theme::new(options.theme);
+        let focus = if options.connection_id.is_some() {
+            Focus::IndividualStats
+        } else {
+            Focus::AggregatedStats
+        };
         Self {
             options,
+            theme,
             history,
             aggregated_table_state: TableState::default(),
             individual_table_state: TableState::default(),
-            focus: Focus::AggregatedStats,
+            groups_table_state: TableState::default(),
+            focus,
+            click_targets: Vec::new(),
+            max_delta_rows: Vec::new(),
             pause: false,
+            paused_on_alert: false,
+            alert_log: VecDeque::new(),
+            alert_log_table_state: None,
             realtime,
             poll_failed_count: 0,
+            last_poll_error: None,
             editing_stats_key_filter: None,
+            pinned_keys,
+            split_by_media,
+            media_sections,
+            selected_keys: Vec::new(),
+            compare_keys: Vec::new(),
+            confirming_quit: false,
+            show_totals: false,
+            totals: SessionTotals::default(),
+            show_moving_average: false,
+            show_ratio: false,
+            zoom: false,
+            notice: None,
+            show_delta_detail: false,
+            show_value_types: false,
+            connection_detail: None,
+            chart_lock: None,
+            show_histogram: false,
+            natural_sort: false,
+            show_unfiltered_count: true,
+            hidden_connections: BTreeSet::new(),
+            show_average: false,
+            help_page: 0,
+            individual_hscroll: 0,
+            connection_churn: None,
+            annotations: Vec::new(),
+            editing_annotation: None,
+            key_search: None,
+            testing_connection_filter: None,
+            filter_preset: None,
+            default_stats_key_filter,
             eof: false,
+            schema_warning: None,
+            record_metadata: None,
             end_pos: 0,
         }
     }
 
+    fn update_totals(&mut self) {
+        if self.realtime {
+            let stats = self.history.back().expect("unreachable");
+            self.totals.add(&stats.aggregated, 1.0);
+        } else {
+            let target = std::cmp::max(self.end_pos, self.totals.base_pos);
+            while self.totals.pos < target {
+                self.totals
+                    .add(&self.history[self.totals.pos].aggregated, 1.0);
+                self.totals.pos += 1;
+            }
+            while self.totals.pos > target {
+                self.totals.pos -= 1;
+                self.totals
+                    .add(&self.history[self.totals.pos].aggregated, -1.0);
+            }
+        }
+    }
+
+    fn add_annotation(&mut self, label: String) {
+        let time = if self.realtime {
+            SystemTime::now()
+        } else {
+            self.latest_stats().time
+        };
+        self.annotations.push(Annotation { time, label });
+    }
+
+    fn update_connection_churn(&mut self, stats: &Stats) {
+        let prev = self.history.back().expect("unreachable");
+        let added = stats
+            .connections
+            .keys()
+            .filter(|id| {
+                !prev.connections.contains_key(*id) && !prev.hidden_connections.contains_key(*id)
+            })
+            .count();
+        let removed = prev
+            .connections
+            .keys()
+            .chain(prev.hidden_connections.keys())
+            .filter(|id| !stats.connections.contains_key(*id))
+            .count();
+        if added > 0 || removed > 0 {
+            self.connection_churn = Some(ConnectionChurn {
+                added,
+                removed,
+                time: Instant::now(),
+            });
+        } else if self
+            .connection_churn
+            .as_ref()
+            .is_some_and(|c| c.time.elapsed() > CONNECTION_CHURN_DISPLAY_DURATION)
+        {
+            self.connection_churn = None;
+        }
+    }
+
+    fn push_history(&mut self, mut stats: Stats) {
+        if !self.hidden_connections.is_empty() {
+            stats.set_hidden_connections(&self.hidden_connections);
+        }
+        self.history.push_back(stats);
+    }
+
+    /// Hides the selected connection, or unhides all connections if no connection is selected.
+    fn toggle_hidden_connection(&mut self) {
+        if let Some(id) = self.selected_connection_id() {
+            log::info!("hide connection {id:?}");
+            self.hidden_connections.insert(id.to_owned());
+        } else if !self.hidden_connections.is_empty() {
+            log::info!("unhide {} connections", self.hidden_connections.len());
+            self.hidden_connections.clear();
+        } else {
+            return;
+        }
+        for stats in &mut self.history {
+            stats.set_hidden_connections(&self.hidden_connections);
+        }
+        if !self.realtime {
+            self.totals.values.clear();
+            self.totals.pos = self.totals.base_pos;
+            self.update_totals();
+        }
+    }
+
+    fn reset_totals(&mut self) {
+        self.totals = SessionTotals {
+            values: BTreeMap::new(),
+            pos: self.end_pos,
+            base_pos: self.end_pos,
+        };
+    }
+
+    /// Switches to the next filter preset (or back to `--stats-key-filter` after the last one).
+    fn cycle_filter_preset(&mut self) {
+        let presets = &self.options.filter_presets;
+        if presets.is_empty() {
+            return;
+        }
+
+        self.filter_preset = match self.filter_preset {
+            None => Some(0),
+            Some(i) if i + 1 < presets.len() => Some(i + 1),
+            Some(_) => None,
+        };
+        self.options.stats_key_filter = if let Some(i) = self.filter_preset {
+            presets[i].1.clone()
+        } else {
+            self.default_stats_key_filter.clone()
+        };
+        self.ensure_table_indices_are_in_ranges();
+    }
+
+    fn is_recording(&self) -> bool {
+        self.realtime && self.options.record.is_some()
+    }
+
+    fn sum_label(&self) -> &'static str {
+        if self.show_average {
+            "Avg"
+        } else {
+            "Sum"
+        }
+    }
+
+    fn delta_label(&self) -> &'static str {
+        if self.options.percent_delta {
+            "Delta%/s"
+        } else {
+            "Delta/s"
+        }
+    }
+
+    fn format_sum(
+        &self,
+        key: &str,
+        item: &AggregatedStatsItemValue,
+        connection_count: usize,
+    ) -> String {
+        let ctx = self.options.format_context(key);
+        if self.show_average {
+            item.format_value_avg(connection_count, ctx)
+        } else {
+            item.format_value_sum(ctx)
+        }
+    }
+
+    fn active_alerts(&self) -> Vec<Alert> {
+        let stats = self.latest_stats();
+        self.options
+            .alerts
+            .iter()
+            .flat_map(|rule| rule.evaluate(stats))
+            .collect()
+    }
+
+    /// Appends the alerts that are not in `prev_alerts` (i.e., newly fired) to the alert log.
+    fn log_new_alerts(&mut self, prev_alerts: &BTreeSet<(String, ConnectionId)>) {
+        let timestamp = self.latest_stats().timestamp;
+        for alert in self.active_alerts() {
+            if prev_alerts.contains(&(alert.rule.to_string(), alert.connection_id.clone())) {
+                continue;
+            }
+            if self.alert_log.len() == self.options.alert_log_size.get() {
+                self.alert_log.pop_front();
+            }
+            self.alert_log.push_back((timestamp, alert));
+        }
+    }
+
+    fn active_alert_ids(&self) -> BTreeSet<(String, ConnectionId)> {
+        self.active_alerts()
+            .into_iter()
+            .map(|alert| (alert.rule.to_string(), alert.connection_id))
+            .collect()
+    }
+
     fn latest_stats(&self) -> &Stats {
         if self.realtime {
             self.history.back().expect("unreachable")
@@ -335,6 +1131,16 @@ impl UiState {
         }
     }
 
+    /// Returns the snapshot just before `latest_stats()` (i.e., the base of the current deltas).
+    fn previous_stats(&self) -> Option<&Stats> {
+        let pos = if self.realtime {
+            self.history.len()
+        } else {
+            self.end_pos
+        };
+        pos.checked_sub(2).map(|i| &self.history[i])
+    }
+
     #[allow(clippy::iter_skip_zero)]
     fn history_window(&self) -> (Duration, impl Iterator<Item = &Stats>) {
         if self.realtime {
@@ -360,24 +1166,554 @@ impl UiState {
     }
 
     fn render(&mut self, f: &mut Frame) {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints(
-                [
-                    Constraint::Length(5),
-                    Constraint::Min(0),
-                    Constraint::Length(3),
-                ]
-                .as_ref(),
-            )
-            .split(f.area());
-
-        self.render_header(f, chunks[0]);
-        self.render_body(f, chunks[1]);
-        self.render_footer(f, chunks[2]);
-    }
-
-    fn render_header(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        self.click_targets.clear();
+        self.max_delta_rows.clear();
+        let area = f.area();
+        if area.width < MIN_TERMINAL_WIDTH || area.height < MIN_TERMINAL_HEIGHT {
+            let message = format!(
+                "Terminal too small ({}x{}, need at least {MIN_TERMINAL_WIDTH}x{MIN_TERMINAL_HEIGHT})",
+                area.width, area.height
+            );
+            let paragraph = Paragraph::new(message)
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true });
+            f.render_widget(paragraph, area);
+            return;
+        }
+        self.render_layout(f);
+        if self.show_delta_detail {
+            self.render_delta_detail(f);
+        }
+        if self.show_value_types {
+            self.render_value_types(f);
+        }
+        if self.testing_connection_filter.is_some() {
+            let lines = self.connection_filter_test_lines();
+            self.render_popup(f, "Connection Filter Tester (Enter / Esc to close)", lines);
+        }
+        if self.connection_detail.is_some() {
+            self.render_connection_detail(f);
+        }
+        if self.alert_log_table_state.is_some() {
+            self.render_alert_log(f);
+        }
+    }
+
+    fn render_layout(&mut self, f: &mut Frame) {
+        if self.zoom {
+            self.render_zoom(f);
+            return;
+        }
+        if self.options.compact || f.area().height < COMPACT_LAYOUT_AUTO_HEIGHT {
+            self.render_compact(f);
+            return;
+        }
+
+        let header_height = std::cmp::max(self.status_lines().len(), HELP_MIN_LINES) + 2;
+        let scrubber_height = if self.options.mouse && !self.realtime {
+            1
+        } else {
+            0
+        };
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(header_height as u16),
+                    Constraint::Length(scrubber_height),
+                    Constraint::Min(0),
+                    Constraint::Length(3),
+                ]
+                .as_ref(),
+            )
+            .split(f.area());
+
+        self.render_header(f, chunks[0]);
+        if scrubber_height > 0 {
+            self.render_scrubber(f, chunks[1]);
+        }
+        self.render_body(f, chunks[2]);
+        self.render_footer(f, chunks[3]);
+    }
+
+    fn render_scrubber(&mut self, f: &mut Frame, area: Rect) {
+        let len = self.history.len();
+        let ratio = if len == 0 {
+            0.0
+        } else {
+            self.end_pos as f64 / len as f64
+        };
+        let gauge = ratatui::widgets::LineGauge::default()
+            .filled_style(self.theme.scrubber)
+            .label(format!("{}/{} ", self.end_pos, len))
+            .ratio(ratio);
+        f.render_widget(gauge, area);
+        self.click_targets.push((ClickTarget::Scrubber, area));
+    }
+
+    fn render_zoom(&mut self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length(BIG_TEXT_HEIGHT as u16 + 4),
+                    Constraint::Min(0),
+                    Constraint::Length(3),
+                ]
+                .as_ref(),
+            )
+            .split(f.area());
+
+        self.render_zoomed_value(f, chunks[0]);
+        self.render_chart(f, chunks[1]);
+        self.render_footer(f, chunks[2]);
+    }
+
+    fn render_compact(&mut self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+            .split(f.area());
+        self.render_status_bar(f, chunks[0]);
+
+        let body = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(chunks[1]);
+        match self.focus {
+            Focus::AggregatedStats => self.render_aggregated_stats(f, body[0]),
+            Focus::Groups => self.render_groups(f, body[0]),
+            Focus::IndividualStats => self.render_individual_stats(f, body[0]),
+        }
+        self.render_chart(f, body[1]);
+    }
+
+    fn render_status_bar(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        if let Some(editing) = &self.editing_stats_key_filter {
+            let label = "[EDITING KEY FILTER (Enter to finish)] ";
+            let style = if editing.valid {
+                self.theme.ok
+            } else {
+                self.theme.error
+            };
+            let line = Line::styled(format!("{label}{}", editing.text), style);
+            f.set_cursor_position((area.x + (label.len() + editing.cursor) as u16, area.y));
+            f.render_widget(Paragraph::new(line), area);
+            return;
+        }
+        if let Some(label) = &self.editing_annotation {
+            let prompt = "[ANNOTATION LABEL (Enter to add, Esc to cancel)] ";
+            f.set_cursor_position((area.x + (prompt.len() + label.len()) as u16, area.y));
+            f.render_widget(Paragraph::new(format!("{prompt}{label}")), area);
+            return;
+        }
+        if let Some(search) = &self.key_search {
+            let prompt = search.prompt();
+            let cursor = prompt.len() + search.query.len();
+            f.set_cursor_position((area.x + cursor as u16, area.y));
+            f.render_widget(Paragraph::new(format!("{prompt}{}", search.query)), area);
+            return;
+        }
+        if self.confirming_quit {
+            let line = Line::styled("Quit and stop recording? (y/n)", self.theme.warning);
+            f.render_widget(Paragraph::new(line), area);
+            return;
+        }
+
+        let stats = self.latest_stats();
+        let capped = if let Some(n) = stats.uncapped_connection_count {
+            format!("/{n}")
+        } else {
+            String::new()
+        };
+        let mut text = format!(
+            "[{}] {} | Connections: {}{capped} | Keys: {}",
+            self.status_title(),
+            match self.options.time_display {
+                TimeDisplay::Absolute => self
+                    .to_display_time(stats.time)
+                    .format("%H:%M:%S")
+                    .to_string(),
+                TimeDisplay::Relative => format_relative_time(stats.timestamp),
+            },
+            stats.connection_count(),
+            stats.filtered_item_count(&self.options.stats_key_filter)
+        );
+        for (label, key) in [("TX", COMPACT_TX_KEY), ("RX", COMPACT_RX_KEY)] {
+            if let Some(item) = stats.aggregated.get(key) {
+                text.push_str(&format!(
+                    " | {label}: {}/s",
+                    item.format_delta_per_sec(self.options.format_context(key))
+                ));
+            }
+        }
+        if self.poll_failed_count > 0 {
+            text.push_str(&format!(" | [ERROR] retried {}", self.poll_failed_count));
+        }
+        let paragraph = Paragraph::new(text).style(self.theme.status_bar);
+        f.render_widget(paragraph, area);
+    }
+
+    fn render_delta_detail(&mut self, f: &mut Frame) {
+        let lines = self.delta_detail_lines();
+        self.render_popup(f, "Delta Detail ('i' to close)", lines);
+    }
+
+    fn render_value_types(&mut self, f: &mut Frame) {
+        let lines = self.value_type_lines();
+        self.render_popup(f, "Value Types ('c' to close)", lines);
+    }
+
+    fn render_popup(&self, f: &mut Frame, title: &str, lines: Vec<Line<'static>>) {
+        let width = lines.iter().map(|l| l.width()).max().unwrap_or(0) + 4;
+        let height = lines.len() + 2;
+        let area = f.area();
+        let width = std::cmp::min(std::cmp::max(width, 40) as u16, area.width);
+        let height = std::cmp::min(height as u16, area.height);
+        let area = ratatui::layout::Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let paragraph = Paragraph::new(lines)
+            .block(self.make_block(title, None))
+            .alignment(Alignment::Left);
+        f.render_widget(Clear, area);
+        f.render_widget(paragraph, area);
+    }
+
+    fn render_connection_detail(&mut self, f: &mut Frame) {
+        let Some(detail) = &self.connection_detail else {
+            return;
+        };
+        let area = f.area();
+        let width = area.width * 4 / 5;
+        let height = area.height * 4 / 5;
+        let area = ratatui::layout::Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let connection = self.latest_stats().connections.get(&detail.connection_id);
+        let item_count = connection.map_or(0, |c| c.items.len());
+        let mut value_width = 0;
+        let mut delta_width = 0;
+        let row_items = connection
+            .map(|c| {
+                c.items
+                    .iter()
+                    .map(|(k, item)| {
+                        let ctx = self.options.format_context(k);
+                        let value = item.format_value(ctx);
+                        let delta = item.format_delta_per_sec(ctx);
+                        value_width = std::cmp::max(value_width, value.len());
+                        delta_width = std::cmp::max(delta_width, delta.len());
+                        (k.clone(), value, delta)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let rows = row_items.into_iter().map(|(k, value, delta)| {
+            Row::new(vec![
+                Cell::from(k),
+                Cell::from(format!("{:>value_width$}", value)),
+                Cell::from(format!("{:>delta_width$}", delta)),
+            ])
+        });
+        let header_cells = ["Key", "Value", self.delta_label()]
+            .into_iter()
+            .map(|h| Cell::from(h).style(self.theme.header));
+        let header = Row::new(header_cells).bottom_margin(1);
+
+        let mut title = format!(
+            "Connection {}",
+            self.options.display_connection_id(&detail.connection_id)
+        );
+        if connection.is_none() {
+            title.push_str(" [DISCONNECTED]");
+        }
+        title.push_str(" ('d' to close)");
+        let highlight_symbol = format!(
+            "{:>width$}> ",
+            detail.table_state.selected().unwrap_or(0) + 1,
+            width = item_count.to_string().len()
+        );
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(50),
+                Constraint::Percentage(30),
+                Constraint::Percentage(20),
+            ],
+        )
+        .header(header)
+        .block(self.make_block(&title, None))
+        .row_highlight_style(self.theme.highlight)
+        .highlight_symbol(highlight_symbol);
+        f.render_widget(Clear, area);
+        let detail = self.connection_detail.as_mut().expect("unreachable");
+        f.render_stateful_widget(table, area, &mut detail.table_state);
+    }
+
+    fn render_alert_log(&mut self, f: &mut Frame) {
+        let area = f.area();
+        let width = area.width * 4 / 5;
+        let height = area.height * 4 / 5;
+        let area = ratatui::layout::Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let rows = self.alert_log.iter().rev().map(|(timestamp, alert)| {
+            let rule = &alert.rule;
+            let key = if rule.per_sec {
+                format!("{}/s", rule.key)
+            } else {
+                rule.key.clone()
+            };
+            Row::new(vec![
+                Cell::from(self.format_time(alert.time, *timestamp)),
+                Cell::from(key),
+                Cell::from(self.options.display_connection_id(&alert.connection_id)),
+                Cell::from(alert.value.to_string()),
+                Cell::from(format!("{}{}", rule.op, rule.threshold)),
+            ])
+        });
+        let header_cells = ["Time", "Key", "Connection", "Value", "Threshold"]
+            .into_iter()
+            .map(|h| Cell::from(h).style(self.theme.header));
+        let header = Row::new(header_cells).bottom_margin(1);
+
+        let title = format!(
+            "Alert Log ({} alerts, newest first, 'a' to close)",
+            self.alert_log.len()
+        );
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(25),
+                Constraint::Percentage(25),
+                Constraint::Percentage(20),
+                Constraint::Percentage(15),
+                Constraint::Percentage(15),
+            ],
+        )
+        .header(header)
+        .block(self.make_block(&title, None))
+        .row_highlight_style(self.theme.highlight)
+        .highlight_symbol("> ");
+        f.render_widget(Clear, area);
+        let state = self.alert_log_table_state.as_mut().expect("unreachable");
+        f.render_stateful_widget(table, area, state);
+    }
+
+    fn delta_detail_lines(&self) -> Vec<Line<'static>> {
+        let (Some(key), Some(id)) = (self.chart_key(), self.selected_connection_id()) else {
+            return vec![Line::from(
+                " Select a connection to see how its delta is computed",
+            )];
+        };
+
+        let mut lines = vec![
+            Line::from(format!(" Key:            {key}")),
+            Line::from(format!(
+                " Connection:     {}",
+                self.options.display_connection_id(id)
+            )),
+            Line::from(""),
+        ];
+        let curr = self.latest_stats().connections.get(id);
+        let prev = self.previous_stats().and_then(|s| s.connections.get(id));
+        for (label, conn) in [("Previous", prev), ("Current", curr)] {
+            if let Some((conn, item)) = conn.and_then(|c| Some((c, c.items.get(key)?))) {
+                lines.push(Line::from(format!(
+                    " {:<15} {} (at {})",
+                    format!("{label} value:"),
+                    item.value,
+                    conn.timestamp
+                        .to_rfc3339_opts(chrono::SecondsFormat::Millis, false)
+                )));
+            } else {
+                lines.push(Line::from(format!(
+                    " {:<15} (none)",
+                    format!("{label} value:")
+                )));
+            }
+        }
+
+        let dt = curr
+            .zip(prev)
+            .and_then(|(curr, prev)| (curr.timestamp - prev.timestamp).to_std().ok());
+        let item = curr.and_then(|c| c.items.get(key));
+        let format_opt = |v: Option<String>| v.unwrap_or_else(|| "(none)".to_owned());
+        lines.push(Line::from(format!(
+            " dt:             {}",
+            format_opt(dt.map(|dt| format!("{:.3}s", dt.as_secs_f64())))
+        )));
+        lines.push(Line::from(format!(
+            " Delta:          {}",
+            format_opt(item.and_then(|i| i.delta).map(|v| v.to_string()))
+        )));
+        lines.push(Line::from(format!(
+            " Delta/s:        {}",
+            format_opt(item.and_then(|i| i.delta_per_sec).map(|v| v.to_string()))
+        )));
+        lines
+    }
+
+    /// Classifies the values of the selected key reported by the connections (to spot schema inconsistencies).
+    fn value_type_lines(&self) -> Vec<Line<'static>> {
+        let Some(key) = self.chart_key() else {
+            return vec![Line::from(" Select a key to see the types of its values")];
+        };
+
+        let (mut number, mut bool, mut string, mut missing) = (0, 0, 0, 0);
+        for connection in self.latest_stats().connections.values() {
+            match connection.items.get(key).map(|item| &item.value) {
+                Some(
+                    StatsItemValue::Number(_) | StatsItemValue::UInt(_) | StatsItemValue::Int(_),
+                ) => number += 1,
+                Some(StatsItemValue::Bool(_)) => bool += 1,
+                Some(StatsItemValue::String(_)) => string += 1,
+                None => missing += 1,
+            }
+        }
+        let mut lines = vec![
+            Line::from(format!(" Key:      {key}")),
+            Line::from(""),
+            Line::from(format!(" Number:   {number}")),
+            Line::from(format!(" Bool:     {bool}")),
+            Line::from(format!(" String:   {string}")),
+            Line::from(format!(" Missing:  {missing}")),
+        ];
+        if [number, bool, string]
+            .into_iter()
+            .filter(|n| *n > 0)
+            .count()
+            > 1
+        {
+            lines.push(Line::from(""));
+            lines.push(Line::styled(
+                " [WARNING] Mixed value types (only numbers are aggregated)",
+                self.theme.warning,
+            ));
+        }
+        lines
+    }
+
+    /// Previews which connections in the latest snapshot would match the pattern being typed (not applied).
+    fn connection_filter_test_lines(&self) -> Vec<Line<'static>> {
+        const MAX_SAMPLES: usize = 10;
+
+        let pattern = self.testing_connection_filter.clone().unwrap_or_default();
+        let mut lines = vec![Line::from(format!(" Pattern: {pattern}_")), Line::from("")];
+        let regex = match Regex::new(&pattern) {
+            Ok(regex) => regex,
+            Err(e) => {
+                lines.push(Line::styled(
+                    format!(
+                        " Invalid regex: {}",
+                        e.to_string().lines().last().unwrap_or("")
+                    ),
+                    self.theme.error,
+                ));
+                return lines;
+            }
+        };
+
+        let connections = &self.latest_stats().connections;
+        let matches = connections
+            .values()
+            .filter_map(|c| {
+                let target = c.filter_targets().find(|s| regex.is_match(s))?;
+                Some((c.connection_id.as_str(), target))
+            })
+            .collect::<Vec<_>>();
+        lines.push(Line::from(format!(
+            " Matches {} of {} connections in the current snapshot",
+            matches.len(),
+            connections.len()
+        )));
+        lines.push(Line::from(""));
+        for (id, target) in matches.iter().take(MAX_SAMPLES) {
+            lines.push(Line::from(format!(
+                " {}: {target}",
+                self.options.display_connection_id(id)
+            )));
+        }
+        if matches.len() > MAX_SAMPLES {
+            lines.push(Line::from(format!(
+                " ... and {} more",
+                matches.len() - MAX_SAMPLES
+            )));
+        }
+        lines
+    }
+
+    fn render_zoomed_value(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let Some(key) = self.chart_key() else {
+            f.render_widget(self.make_block("Value", None), area);
+            return;
+        };
+
+        let connection_id = self.selected_connection_id();
+        let (value, delta) = if let Some(id) = connection_id {
+            self.latest_stats()
+                .connections
+                .get(id)
+                .and_then(|c| c.items.get(key))
+                .map(|item| {
+                    (
+                        item.format_value(self.options.format_context(key)),
+                        item.format_delta_per_sec(self.options.format_context(key)),
+                    )
+                })
+        } else {
+            self.latest_stats().aggregated.get(key).map(|item| {
+                (
+                    self.format_sum(key, item, self.latest_stats().connection_count()),
+                    item.format_delta_per_sec(self.options.format_context(key)),
+                )
+            })
+        }
+        .unwrap_or_default();
+
+        let mut title = if delta.is_empty() {
+            format!("Value of {key:?}")
+        } else {
+            format!("Delta/s of {key:?}")
+        };
+        if let Some(id) = connection_id {
+            title.push_str(&format!(" ({})", self.options.display_connection_id(id)));
+        }
+
+        let mut lines = if delta.is_empty() {
+            big_text_lines(&value)
+        } else {
+            big_text_lines(&delta)
+        };
+        lines.push(Line::from(""));
+        if delta.is_empty() {
+            lines.push(Line::from(value));
+        } else if connection_id.is_some() {
+            lines.push(Line::from(format!("Value: {value}")));
+        } else {
+            lines.push(Line::from(format!("{}: {value}", self.sum_label())));
+        }
+
+        let paragraph = Paragraph::new(lines)
+            .block(self.make_block(&title, None))
+            .alignment(Alignment::Center);
+        f.render_widget(paragraph, area);
+    }
+
+    fn render_header(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
@@ -389,105 +1725,350 @@ impl UiState {
 
     fn render_footer(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
         let mut text = vec![];
-        if let Some(editing) = &self.editing_stats_key_filter {
+        if self.confirming_quit {
+            text.push(Line::styled(
+                "Quit and stop recording? (y/n)",
+                self.theme.warning,
+            ));
+        } else if let Some(editing) = &self.editing_stats_key_filter {
             let label = "[EDITING KEY FILTER (Enter to finish)] ";
             text.push(Line::from(format!("{label}{}", editing.text)));
             f.set_cursor_position((
                 area.x + 1 + (label.len() + editing.cursor) as u16,
                 area.y + 1,
             ));
+        } else if let Some(label) = &self.editing_annotation {
+            let prompt = "[ANNOTATION LABEL (Enter to add, Esc to cancel)] ";
+            text.push(Line::from(format!("{prompt}{label}")));
+            f.set_cursor_position((area.x + 1 + (prompt.len() + label.len()) as u16, area.y + 1));
+        } else if let Some(search) = &self.key_search {
+            let prompt = search.prompt();
+            let cursor = prompt.len() + search.query.len();
+            text.push(Line::from(format!("{prompt}{}", search.query)));
+            f.set_cursor_position((area.x + 1 + cursor as u16, area.y + 1));
+        } else if let Some(notice) = &self.notice {
+            text.push(Line::from(notice.clone()));
         } else if let Some(key) = self.selected_item_key() {
-            text.push(Line::from(format!("[KEY] {}", key)));
+            if let Some(id) = self.selected_connection_id() {
+                text.push(Line::from(format!("[KEY] {} [CONNECTION] {}", key, id)));
+            } else {
+                text.push(Line::from(format!("[KEY] {}", key)));
+            }
         } else if self.poll_failed_count > 0 {
             text.push(Line::from(format!(
-                "[ERROR] Cannot connect to {} (retried {} times)",
-                self.options.sora_api_url, self.poll_failed_count
+                "[ERROR] Cannot connect to {} (retried {} times): {}",
+                self.options.sora_api_url,
+                self.poll_failed_count,
+                self.last_poll_error.as_deref().unwrap_or("unknown error")
             )));
         }
 
         let mut paragraph = Paragraph::new(text)
-            .block(Block::default().borders(Borders::ALL))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(self.theme.border),
+            )
             .alignment(Alignment::Left);
         if let Some(editing) = &self.editing_stats_key_filter {
             if editing.valid {
-                paragraph = paragraph.style(Style::default().fg(Color::Green));
+                paragraph = paragraph.style(self.theme.ok);
             } else {
-                paragraph = paragraph.style(Style::default().fg(Color::Red));
+                paragraph = paragraph.style(self.theme.error);
             }
         }
         f.render_widget(paragraph, area);
     }
 
-    fn render_status(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
-        let block = if self.pause {
-            self.make_block("Status (PAUSED)", None)
+    fn status_title(&self) -> String {
+        if self.paused_on_alert {
+            "PAUSED ON ALERT".to_owned()
+        } else if self.pause {
+            "PAUSED".to_owned()
         } else if !self.realtime {
+            let mut title = "REPLAY".to_owned();
+            if let Some(label) = self.record_metadata.as_ref().and_then(|m| m.label.as_ref()) {
+                title.push_str(&format!(": {label}"));
+            }
             if self.eof && self.end_pos == self.history.len() {
-                self.make_block("Status (REPLAY, EOF)", None)
-            } else {
-                self.make_block("Status (REPLAY)", None)
+                title.push_str(", EOF");
+            } else if self.options.follow {
+                title.push_str(", FOLLOW");
             }
+            title
+        } else {
+            "LIVE".to_owned()
+        }
+    }
+
+    fn render_status(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let block = if self.pause || !self.realtime {
+            self.make_block(&format!("Status ({})", self.status_title()), None)
         } else {
             self.make_block("Status", None)
         };
 
+        let paragraph = Paragraph::new(self.status_lines())
+            .block(block)
+            .alignment(Alignment::Left);
+        f.render_widget(paragraph, area);
+    }
+
+    fn status_lines(&self) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        let alerts = self.active_alerts();
+        if let Some(alert) = alerts.first() {
+            let others = if alerts.len() > 1 {
+                format!(" (and {} more)", alerts.len() - 1)
+            } else {
+                String::new()
+            };
+            let resume = if self.paused_on_alert {
+                ", 'p' to resume"
+            } else {
+                ""
+            };
+            lines.push(Line::styled(
+                format!(
+                    "[ALERT] {} (connection={}, value={}){others}{resume}",
+                    alert.rule,
+                    self.options.display_connection_id(&alert.connection_id),
+                    alert.value
+                ),
+                self.theme.alert,
+            ));
+        }
+        lines.extend([
+            self.update_time_status_line(),
+            self.connections_status_line(),
+            self.keys_status_line(),
+        ]);
+        if self.realtime && self.options.insecure {
+            lines.push(Line::styled(
+                "[WARNING] TLS certificate verification is disabled (--insecure)",
+                self.theme.warning,
+            ));
+        }
+        if self.history.iter().any(|s| s.clock_skew) {
+            lines.push(Line::styled(
+                "[WARNING] Clock skew detected (timestamps went backwards)",
+                self.theme.warning,
+            ));
+        }
+        if let Some(note) = self.record_metadata.as_ref().and_then(|m| m.note.as_ref()) {
+            lines.push(Line::from(format!("[NOTE] {note}")));
+        }
+        if let Some(warning) = &self.schema_warning {
+            lines.push(Line::styled(
+                format!("[WARNING] {warning}"),
+                self.theme.warning,
+            ));
+        }
+        lines
+    }
+
+    fn update_time_status_line(&self) -> Line<'static> {
         let stats = self.latest_stats();
-        let paragraph = Paragraph::new(vec![
-            Line::from(format!(
-                "Update Time: {}",
-                chrono::DateTime::<chrono::Local>::from(stats.time)
-                    .to_rfc3339_opts(chrono::SecondsFormat::Millis, false)
-            )),
-            Line::from(format!(
-                "Connections: {:5} (filter={})",
-                stats.connection_count(),
-                self.options.connection_filter
-            )),
-            Line::from(format!(
-                "Stats  Keys: {:5} (filter={}{})",
-                stats.filtered_item_count(&self.options.stats_key_filter),
-                self.options.stats_key_filter,
-                if self.editing_stats_key_filter.is_some() {
-                    ""
+        let mut spans = vec![Span::from(format!(
+            "Update Time: {}",
+            self.format_time(stats.time, stats.timestamp)
+        ))];
+        if self.realtime && self.poll_failed_count > 0 {
+            spans.push(Span::styled(
+                format!(
+                    " (last poll failed: {}, retried {})",
+                    self.last_poll_error.as_deref().unwrap_or("unknown error"),
+                    self.poll_failed_count
+                ),
+                self.theme.error,
+            ));
+        } else if let Some(request) = stats.request_status.filter(|_| self.realtime) {
+            let text = format!(
+                " (last poll: {} in {}ms)",
+                request.status,
+                request.latency.as_millis()
+            );
+            let interval = Duration::from_secs(self.options.polling_interval.get() as u64);
+            if request.latency >= interval {
+                spans.push(Span::styled(text, self.theme.warning));
+            } else {
+                spans.push(Span::from(text));
+            }
+            if let Some(actual) = request.interval {
+                let text = format!(
+                    " interval: {:.2}s (target {:.2}s)",
+                    actual.as_secs_f64(),
+                    interval.as_secs_f64()
+                );
+                // Small delays are expected as the interval includes the scheduling overhead.
+                if actual > interval.mul_f64(1.1) {
+                    spans.push(Span::styled(text, self.theme.warning));
                 } else {
-                    ", '/' to edit"
+                    spans.push(Span::from(text));
                 }
-            )),
-        ])
-        .block(block)
-        .alignment(Alignment::Left);
-        f.render_widget(paragraph, area);
+            }
+        }
+        Line::from(spans)
     }
 
-    fn render_help(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
-        let paragraph = Paragraph::new(vec![
-            Line::from("Quit:           'q' key"),
-            if self.realtime {
-                Line::from("Pause / Resume: 'p' key")
+    fn keys_status_line(&self) -> Line<'static> {
+        let stats = self.latest_stats();
+        let filtered = stats.filtered_item_count(&self.options.stats_key_filter);
+        let mut spans = vec![Span::from(format!(
+            "Stats  Keys: {:5} (filter={}{}{})",
+            filtered,
+            self.options.stats_key_filter,
+            if let Some(i) = self.filter_preset {
+                format!(", preset={}", self.options.filter_presets[i].0)
             } else {
-                Line::from("Prev / Next:    'h' / 'l' keys")
+                String::new()
             },
-            Line::from("Move:           UP / DOWN / LEFT / RIGHT keys"),
-        ])
-        .block(self.make_block("Help", None))
-        .alignment(Alignment::Left);
+            if self.editing_stats_key_filter.is_some() {
+                ""
+            } else {
+                ", '/' to edit"
+            }
+        ))];
+        let total = stats.item_count();
+        if filtered < total {
+            spans.push(Span::styled(
+                format!(" [showing {filtered} of {total} keys (filtered)]"),
+                self.theme.warning,
+            ));
+        }
+        Line::from(spans)
+    }
+
+    fn connections_status_line(&self) -> Line<'static> {
+        let stats = self.latest_stats();
+        let count = match stats.unfiltered_connection_count {
+            Some(n) if self.show_unfiltered_count => {
+                format!("{}/{}", stats.connection_count(), format_u64(n as u64))
+            }
+            _ => stats.connection_count().to_string(),
+        };
+        let mut spans = vec![Span::from(format!(
+            "Connections: {count:>5} (filter={}{})",
+            self.options.connection_filter,
+            if self.natural_sort {
+                ", order=natural"
+            } else {
+                ""
+            }
+        ))];
+        if stats.unfiltered_connection_count.is_some() && !self.show_unfiltered_count {
+            spans.push(Span::styled(
+                " [filtered, 'C' to show the total]",
+                self.theme.warning,
+            ));
+        }
+        if let Some(n) = stats.uncapped_connection_count {
+            spans.push(Span::styled(
+                format!(" [CAPPED from {}]", format_u64(n as u64)),
+                self.theme.warning,
+            ));
+        }
+        if !self.hidden_connections.is_empty() {
+            spans.push(Span::styled(
+                format!(
+                    " ({} hidden, 'H' outside the connection table to unhide)",
+                    self.hidden_connections.len()
+                ),
+                self.theme.warning,
+            ));
+        }
+        if stats.duplicate_connection_count > 0 {
+            spans.push(Span::styled(
+                format!(
+                    " [{} duplicate IDs, suffixed with '#N']",
+                    stats.duplicate_connection_count
+                ),
+                self.theme.warning,
+            ));
+        }
+        if let Some(churn) = &self.connection_churn {
+            spans.push(Span::from(" "));
+            spans.push(Span::styled(format!("+{}", churn.added), self.theme.ok));
+            spans.push(Span::from(" / "));
+            spans.push(Span::styled(
+                format!("-{}", churn.removed),
+                self.theme.error,
+            ));
+        }
+        Line::from(spans)
+    }
+
+    fn render_help(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let lines = self.help_lines();
+        let rows = usize::from(area.height.saturating_sub(2)).max(1);
+        if self.help_page * rows >= lines.len() {
+            self.help_page = 0;
+        }
+        let offset = self.help_page * rows;
+        let title = if lines.len() > rows {
+            format!(
+                "Help [{}-{}/{}, '?' for more]",
+                offset + 1,
+                (offset + rows).min(lines.len()),
+                lines.len()
+            )
+        } else {
+            "Help".to_owned()
+        };
+        let paragraph = Paragraph::new(lines)
+            .block(self.make_block(&title, None))
+            .alignment(Alignment::Left)
+            .scroll((offset as u16, 0));
         f.render_widget(paragraph, area);
     }
 
+    fn help_lines(&self) -> Vec<Line<'static>> {
+        let width = KEY_BINDINGS
+            .iter()
+            .map(|b| b.description.len())
+            .max()
+            .unwrap_or(0);
+        KEY_BINDINGS
+            .iter()
+            .filter(|b| match b.context {
+                KeyContext::Always => true,
+                KeyContext::Realtime => self.realtime,
+                KeyContext::Replay => !self.realtime,
+                KeyContext::Focus(focus) => self.focus == focus,
+            })
+            .map(|b| {
+                Line::from(format!(
+                    "{:width$} {}",
+                    format!("{}:", b.description),
+                    b.keys,
+                    width = width + 1
+                ))
+            })
+            .collect()
+    }
+
     fn render_body(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
             .split(area);
+        let (aggregated_area, details_area) = match self.options.layout {
+            ColumnLayout::AggregatedLeft => (chunks[0], chunks[1]),
+            ColumnLayout::DetailsLeft => (chunks[1], chunks[0]),
+        };
 
-        self.render_aggregated_stats(f, chunks[0]);
-        self.render_details(f, chunks[1]);
+        self.render_aggregated_stats(f, aggregated_area);
+        self.render_details(f, details_area);
     }
 
     fn render_aggregated_stats(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
-        let header_cells = ["Key", "Sum", "Delta/s"]
-            .into_iter()
-            .map(|h| Cell::from(h).style(Style::default().add_modifier(Modifier::BOLD)));
+        let header_cells = if self.show_totals {
+            vec!["Key", self.sum_label(), self.delta_label(), "Total"]
+        } else {
+            vec!["Key", self.sum_label(), self.delta_label()]
+        }
+        .into_iter()
+        .map(|h| Cell::from(h).style(self.theme.header));
         let header = Row::new(header_cells).bottom_margin(1);
 
         let item_count = self
@@ -495,35 +2076,98 @@ impl UiState {
             .filtered_item_count(&self.options.stats_key_filter);
         let mut sum_width = 0;
         let mut delta_width = 0;
+        let mut total_width = 0;
         let mut row_items = Vec::with_capacity(item_count);
-        for (k, item) in self
-            .latest_stats()
-            .aggregated
-            .filtered_items(&self.options.stats_key_filter)
-        {
-            let sum = item.format_value_sum();
-            let delta = item.format_delta_per_sec();
+        let max_delta_row = max_index(
+            self.aggregated_items()
+                .into_iter()
+                .map(|(_, item)| item.delta_per_sec),
+        );
+        if let Some(i) = max_delta_row {
+            self.max_delta_rows.push((Focus::AggregatedStats, i));
+        }
+        let connection_count = self.latest_stats().connection_count();
+        for (k, item) in self.aggregated_items() {
+            let ctx = self.options.format_context(k);
+            let sum = self.format_sum(k, item, connection_count);
+            let delta = item.format_delta_per_sec(ctx);
+            let total = self.totals.format_total(k, ctx);
             sum_width = std::cmp::max(sum_width, sum.len());
             delta_width = std::cmp::max(delta_width, delta.len());
-            row_items.push((k.clone(), sum, delta));
+            total_width = std::cmp::max(total_width, total.len());
+            row_items.push((k.clone(), sum, delta, total));
         }
 
-        let rows = row_items.into_iter().map(|(k, sum, delta)| {
-            Row::new(vec![
-                Cell::from(k),
-                Cell::from(format!("{:>sum_width$}", sum)),
-                Cell::from(format!("{:>delta_width$}", delta)),
-            ])
-        });
+        let pinned_count = row_items
+            .iter()
+            .take_while(|(k, _, _, _)| self.pinned_keys.contains(k))
+            .count();
+        let section_width = if self.split_by_media {
+            row_items
+                .iter()
+                .map(|(k, _, _, _)| self.media_section_name(k).len())
+                .max()
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        let rows = row_items
+            .into_iter()
+            .enumerate()
+            .map(|(i, (k, sum, delta, total))| {
+                let style = if self.selected_keys.contains(&k) {
+                    self.theme.selected
+                } else if self.compare_keys.contains(&k) {
+                    Style::default().fg(self.theme.color_for_key(&k))
+                } else if i < pinned_count {
+                    self.theme.pinned
+                } else {
+                    Style::default()
+                };
+                let key_cell = if self.split_by_media && i >= pinned_count {
+                    Cell::from(Line::from(vec![
+                        Span::styled(
+                            format!("{:<section_width$} ", self.media_section_name(&k)),
+                            self.theme.dim,
+                        ),
+                        Span::from(self.options.display_stats_key(&k)),
+                    ]))
+                } else {
+                    Cell::from(self.options.display_stats_key(&k))
+                };
+                let mut cells = vec![
+                    key_cell,
+                    Cell::from(format!("{:>sum_width$}", sum)),
+                    Cell::from(format!("{:>delta_width$}", delta)),
+                ];
+                if self.show_totals {
+                    cells.push(Cell::from(format!("{:>total_width$}", total)));
+                }
+                let row = Row::new(cells).style(style);
+                if i + 1 == pinned_count {
+                    row.bottom_margin(1)
+                } else {
+                    row
+                }
+            });
 
-        let widths = [
-            Constraint::Percentage(60),
-            Constraint::Percentage(20),
-            Constraint::Percentage(20),
-        ];
+        let widths = if self.show_totals {
+            vec![
+                Constraint::Percentage(55),
+                Constraint::Percentage(15),
+                Constraint::Percentage(15),
+                Constraint::Percentage(15),
+            ]
+        } else {
+            vec![
+                Constraint::Percentage(60),
+                Constraint::Percentage(20),
+                Constraint::Percentage(20),
+            ]
+        };
 
         let highlight_style = if self.focus == Focus::AggregatedStats {
-            Style::default().add_modifier(Modifier::REVERSED)
+            self.theme.highlight
         } else {
             Style::default()
         };
@@ -539,9 +2183,30 @@ impl UiState {
             .row_highlight_style(highlight_style)
             .highlight_symbol(highlight_symbol);
         f.render_stateful_widget(table, area, &mut self.aggregated_table_state);
+        self.click_targets
+            .push((ClickTarget::Table(Focus::AggregatedStats), area));
     }
 
     fn render_details(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        if self.options.group_by.is_some() {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(
+                    [
+                        Constraint::Percentage(30),
+                        Constraint::Percentage(35),
+                        Constraint::Percentage(35),
+                    ]
+                    .as_ref(),
+                )
+                .split(area);
+
+            self.render_groups(f, chunks[0]);
+            self.render_individual_stats(f, chunks[1]);
+            self.render_chart(f, chunks[2]);
+            return;
+        }
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
@@ -551,43 +2216,166 @@ impl UiState {
         self.render_chart(f, chunks[1]);
     }
 
+    fn render_groups(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let group_key = self.options.group_by.clone().unwrap_or_default();
+        let selected_key = self.chart_key();
+
+        let stats = self.latest_stats();
+        let mut row_items = vec![(
+            "(all)".to_owned(),
+            stats.connection_count(),
+            &stats.aggregated,
+        )];
+        let groups = stats.groups(&group_key);
+        for (name, group) in &groups {
+            row_items.push((
+                format_group_name(name),
+                group.connection_ids.len(),
+                &group.aggregated,
+            ));
+        }
+
+        let mut count_width = 0;
+        let mut sum_width = 0;
+        let mut delta_width = 0;
+        let row_items = row_items
+            .into_iter()
+            .map(|(name, count, aggregated)| {
+                let (sum, delta) = selected_key
+                    .and_then(|k| aggregated.get(k).map(|item| (k, item)))
+                    .map(|(k, item)| {
+                        (
+                            self.format_sum(k, item, count),
+                            item.format_delta_per_sec(self.options.format_context(k)),
+                        )
+                    })
+                    .unwrap_or_default();
+                let count = format_u64(count as u64);
+                count_width = std::cmp::max(count_width, count.len());
+                sum_width = std::cmp::max(sum_width, sum.len());
+                delta_width = std::cmp::max(delta_width, delta.len());
+                (name, count, sum, delta)
+            })
+            .collect::<Vec<_>>();
+        let rows = row_items.into_iter().map(|(name, count, sum, delta)| {
+            Row::new(vec![
+                Cell::from(name),
+                Cell::from(format!("{:>count_width$}", count)),
+                Cell::from(format!("{:>sum_width$}", sum)),
+                Cell::from(format!("{:>delta_width$}", delta)),
+            ])
+        });
+
+        let header_cells = ["Group", "Connections", self.sum_label(), self.delta_label()]
+            .into_iter()
+            .map(|h| Cell::from(h).style(self.theme.header));
+        let header = Row::new(header_cells).bottom_margin(1);
+        let widths = [
+            Constraint::Percentage(40),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+        ];
+
+        let highlight_style = if self.focus == Focus::Groups {
+            self.theme.highlight
+        } else {
+            Style::default()
+        };
+        let highlight_symbol = format!(
+            "{:>width$}> ",
+            self.groups_table_state.selected().unwrap_or(0) + 1,
+            width = (groups.len() + 1).to_string().len()
+        );
+
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(self.make_block(
+                &format!(
+                    "Groups by {:?} ({:?})",
+                    group_key,
+                    selected_key.unwrap_or("")
+                ),
+                Some(Focus::Groups),
+            ))
+            .row_highlight_style(highlight_style)
+            .highlight_symbol(highlight_symbol);
+        f.render_stateful_widget(table, area, &mut self.groups_table_state);
+        self.click_targets
+            .push((ClickTarget::Table(Focus::Groups), area));
+    }
+
     fn render_individual_stats(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
-        let selected_key = self.selected_item_key();
+        if self.visible_selected_keys().len() > 1 {
+            self.render_multi_key_individual_stats(f, area);
+            return;
+        }
+        let selected_key = self.chart_key();
 
-        let mut row_items = Vec::with_capacity(self.latest_stats().connection_count());
+        let connections = self.visible_connections();
+        let max_delta_row = max_index(connections.iter().map(|c| {
+            selected_key
+                .and_then(|k| c.items.get(k))
+                .and_then(|item| item.delta_per_sec)
+        }));
+        let mut row_items = Vec::with_capacity(connections.len());
         let mut value_width = 0;
         let mut delta_width = 0;
         let mut is_value_num = true;
-        for connection in self.latest_stats().connections.values() {
+        let ctx = self.options.format_context(selected_key.unwrap_or(""));
+        for connection in connections {
             if let Some(item) = selected_key.and_then(|k| connection.items.get(k)) {
-                let value = item.format_value();
-                let delta = item.format_delta_per_sec();
+                let value = item.format_value(ctx);
+                let delta = item.format_delta_per_sec(ctx);
                 is_value_num &= item.value.as_f64().is_some();
                 value_width = std::cmp::max(value_width, value.len());
                 delta_width = std::cmp::max(delta_width, delta.len());
-                row_items.push((connection.connection_id.clone(), value, delta));
+                let transitions = (
+                    format_u64(item.transitions.count as u64),
+                    item.transitions.format_last_changed(connection.timestamp),
+                );
+                row_items.push((
+                    self.options
+                        .display_connection_id(&connection.connection_id),
+                    value,
+                    delta,
+                    transitions,
+                ));
             }
         }
 
-        let rows = row_items.into_iter().map(|(connection_id, value, delta)| {
-            if is_value_num {
-                Row::new(vec![
-                    Cell::from(connection_id),
-                    Cell::from(format!("{:>value_width$}", value)),
-                    Cell::from(format!("{:>delta_width$}", delta)),
-                ])
-            } else {
-                Row::new(vec![Cell::from(connection_id), Cell::from(value)])
-            }
-        });
+        let track_transitions = !is_value_num && self.options.track_transitions;
+        let rows =
+            row_items
+                .into_iter()
+                .map(|(connection_id, value, delta, (changes, last_changed))| {
+                    if is_value_num {
+                        Row::new(vec![
+                            Cell::from(connection_id),
+                            Cell::from(format!("{:>value_width$}", value)),
+                            Cell::from(format!("{:>delta_width$}", delta)),
+                        ])
+                    } else if track_transitions {
+                        Row::new(vec![
+                            Cell::from(connection_id),
+                            Cell::from(value),
+                            Cell::from(changes),
+                            Cell::from(last_changed),
+                        ])
+                    } else {
+                        Row::new(vec![Cell::from(connection_id), Cell::from(value)])
+                    }
+                });
 
         let header_cells = if is_value_num {
-            &["Connection ID", "Value", "Delta/s"][..]
+            vec!["Connection ID", "Value", self.delta_label()]
+        } else if track_transitions {
+            vec!["Connection ID", "Value", "Changes", "Last Changed"]
         } else {
-            &["Connection ID", "Value"][..]
+            vec!["Connection ID", "Value"]
         }
-        .iter()
-        .map(|&h| Cell::from(h).style(Style::default().add_modifier(Modifier::BOLD)));
+        .into_iter()
+        .map(|h| Cell::from(h).style(self.theme.header));
         let header = Row::new(header_cells).bottom_margin(1);
 
         let widths = if is_value_num {
@@ -596,17 +2384,178 @@ impl UiState {
                 Constraint::Percentage(30),
                 Constraint::Percentage(30),
             ]
+        } else if track_transitions {
+            vec![
+                Constraint::Percentage(40),
+                Constraint::Percentage(30),
+                Constraint::Percentage(10),
+                Constraint::Percentage(20),
+            ]
         } else {
             vec![Constraint::Percentage(40), Constraint::Percentage(60)]
         };
 
+        let mut title = format!("Values of {:?}", selected_key.unwrap_or(""));
+        if let Some(group) = self.selected_group() {
+            title.push_str(&format!(" (group: {})", format_group_name(&group)));
+        }
+        let (highlight_style, highlight_symbol) = self.individual_table_highlight();
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(self.make_block(&title, Some(Focus::IndividualStats)))
+            .row_highlight_style(highlight_style)
+            .highlight_symbol(highlight_symbol);
+        f.render_stateful_widget(table, area, &mut self.individual_table_state);
+        self.click_targets
+            .push((ClickTarget::Table(Focus::IndividualStats), area));
+        if let Some(i) = max_delta_row {
+            self.max_delta_rows.push((Focus::IndividualStats, i));
+        }
+    }
+
+    fn render_multi_key_individual_stats(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let keys = self
+            .visible_selected_keys()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let connections = self.visible_connections();
+        let mut row_items = Vec::with_capacity(connections.len());
+        let mut is_value_nums = vec![true; keys.len()];
+        let mut value_widths = vec![0; keys.len()];
+        let mut delta_widths = vec![0; keys.len()];
+        for connection in connections {
+            if !keys.iter().any(|k| connection.items.contains_key(k)) {
+                continue;
+            }
+            let mut values = Vec::with_capacity(keys.len());
+            for (i, k) in keys.iter().enumerate() {
+                let (value, delta) = if let Some(item) = connection.items.get(k) {
+                    is_value_nums[i] &= item.value.as_f64().is_some();
+                    (
+                        item.format_value(self.options.format_context(k)),
+                        item.format_delta_per_sec(self.options.format_context(k)),
+                    )
+                } else {
+                    (String::new(), String::new())
+                };
+                value_widths[i] = std::cmp::max(value_widths[i], value.len());
+                delta_widths[i] = std::cmp::max(delta_widths[i], delta.len());
+                values.push((value, delta));
+            }
+            row_items.push((
+                self.options
+                    .display_connection_id(&connection.connection_id),
+                values,
+            ));
+        }
+
+        // The connection ID column is frozen and the key columns are scrolled horizontally.
+        let id_width = row_items
+            .iter()
+            .map(|(id, _)| id.len())
+            .chain(std::iter::once("Connection ID".len()))
+            .max()
+            .unwrap_or(0);
+        let column_widths = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| {
+                let value_width = std::cmp::max(k.len(), value_widths[i]);
+                if is_value_nums[i] {
+                    vec![
+                        value_width,
+                        std::cmp::max(self.delta_label().len(), delta_widths[i]),
+                    ]
+                } else {
+                    vec![value_width]
+                }
+            })
+            .collect::<Vec<_>>();
+        let (highlight_style, highlight_symbol) = self.individual_table_highlight();
+        let available_width =
+            (area.width as usize).saturating_sub(2 + highlight_symbol.len() + id_width);
+        let start = std::cmp::min(self.individual_hscroll, keys.len() - 1);
+        let mut end = start;
+        let mut used_width = 0;
+        for widths in &column_widths[start..] {
+            let width = widths.iter().map(|w| w + 1).sum::<usize>();
+            if end > start && used_width + width > available_width {
+                break;
+            }
+            used_width += width;
+            end += 1;
+        }
+
+        let rows = row_items.into_iter().map(|(connection_id, values)| {
+            let mut cells = vec![Cell::from(connection_id)];
+            for (i, (value, delta)) in values.into_iter().enumerate().take(end).skip(start) {
+                if is_value_nums[i] {
+                    cells.push(Cell::from(format!("{:>1$}", value, column_widths[i][0])));
+                    cells.push(Cell::from(format!("{:>1$}", delta, column_widths[i][1])));
+                } else {
+                    cells.push(Cell::from(value));
+                }
+            }
+            Row::new(cells)
+        });
+
+        let mut header_cells = vec!["Connection ID"];
+        for (k, &is_value_num) in keys.iter().zip(&is_value_nums).take(end).skip(start) {
+            header_cells.push(k);
+            if is_value_num {
+                header_cells.push(self.delta_label());
+            }
+        }
+        let header = Row::new(
+            header_cells
+                .iter()
+                .map(|&h| Cell::from(h).style(self.theme.header)),
+        )
+        .bottom_margin(1);
+
+        let widths = std::iter::once(id_width)
+            .chain(column_widths[start..end].iter().flatten().copied())
+            .map(|w| Constraint::Length(w as u16));
+
+        let mut title = String::from("Values of ");
+        if start > 0 || end < keys.len() {
+            title.push_str(&format!(
+                "[keys {}-{} of {}, '[' / ']' to scroll] ",
+                start + 1,
+                end,
+                keys.len()
+            ));
+        }
+        title.push_str(
+            &keys[start..end]
+                .iter()
+                .map(|k| format!("{k:?}"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        if let Some(group) = self.selected_group() {
+            title.push_str(&format!(" (group: {})", format_group_name(&group)));
+        }
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(self.make_block(&title, Some(Focus::IndividualStats)))
+            .row_highlight_style(highlight_style)
+            .highlight_symbol(highlight_symbol);
+        f.render_stateful_widget(table, area, &mut self.individual_table_state);
+        self.click_targets
+            .push((ClickTarget::Table(Focus::IndividualStats), area));
+    }
+
+    fn individual_table_highlight(&self) -> (Style, String) {
         let highlight_style = if self.focus == Focus::IndividualStats {
-            Style::default().add_modifier(Modifier::REVERSED)
+            self.theme.highlight
         } else {
             Style::default()
         };
 
-        let cursor_width = (self.latest_stats().connection_count()).to_string().len();
+        let cursor_width = self.visible_connections().len().to_string().len();
         let highlight_symbol = if self.focus == Focus::IndividualStats {
             format!(
                 "{:>width$}> ",
@@ -616,25 +2565,62 @@ impl UiState {
         } else {
             format!("{:>width$}  ", "", width = cursor_width)
         };
-
-        let table = Table::new(rows, widths)
-            .header(header)
-            .block(self.make_block(
-                &format!("Values of {:?}", selected_key.unwrap_or("")),
-                Some(Focus::IndividualStats),
-            ))
-            .row_highlight_style(highlight_style)
-            .highlight_symbol(highlight_symbol);
-        f.render_stateful_widget(table, area, &mut self.individual_table_state);
+        (highlight_style, highlight_symbol)
     }
 
     fn render_chart(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
-        let block = match (self.selected_item_key(), self.selected_connection_id()) {
-            (Some(key), Some(id)) => {
-                self.make_block(&format!("Delta/s Chart of {:?} ({})", key, id), None)
+        if self.show_histogram {
+            self.render_histogram(f, area);
+            return;
+        }
+
+        let ratio_keys = self.ratio_keys();
+        let block = if let Some(lock) = &self.chart_lock {
+            let target = if let Some(id) = &lock.connection_id {
+                self.options.display_connection_id(id)
+            } else {
+                "aggregated".to_owned()
+            };
+            self.make_block(
+                &format!(
+                    "Delta/s Chart of {:?} ({}) [LOCKED, 'L' to unlock]",
+                    lock.key, target
+                ),
+                None,
+            )
+        } else {
+            match (self.chart_key(), self.selected_connection_id()) {
+                _ if ratio_keys.is_some() => {
+                    let (a, b) = ratio_keys.expect("unreachable");
+                    self.make_block(&format!("Ratio Chart of {:?} / {:?}", a, b), None)
+                }
+                (Some(key), Some(id)) => self.make_block(
+                    &format!(
+                        "Delta/s Chart of {:?} ({})",
+                        key,
+                        self.options.display_connection_id(id)
+                    ),
+                    None,
+                ),
+                (Some(key), _) if self.focus == Focus::Groups => {
+                    let group = self
+                        .selected_group()
+                        .map(|g| format_group_name(&g))
+                        .unwrap_or_else(|| "(all)".to_owned());
+                    self.make_block(
+                        &format!("Delta/s Chart of {:?} (group: {})", key, group),
+                        None,
+                    )
+                }
+                (Some(key), _) => {
+                    let mut title = format!("Delta/s Chart of {:?}", key);
+                    for k in self.visible_compare_keys() {
+                        title.push_str(&format!(" vs {:?}", k));
+                    }
+                    self.make_block(&title, None)
+                }
+                _ => self.make_block("Delta/s Chart", None),
             }
-            (Some(key), _) => self.make_block(&format!("Delta/s Chart of {:?}", key), None),
-            _ => self.make_block("Delta/s Chart", None),
         };
 
         let data = self.chart_data();
@@ -642,21 +2628,37 @@ impl UiState {
             f.render_widget(block, area);
             return;
         }
+        let compare_data = self
+            .visible_compare_keys()
+            .into_iter()
+            .map(|k| (k, self.aggregated_key_chart_data(k)))
+            .collect::<Vec<_>>();
 
-        let datasets = vec![Dataset::default()
-            .marker(Marker::Braille)
-            .graph_type(GraphType::Line)
-            .data(&data)];
-
-        let lower_bound = data
-            .iter()
-            .map(|(_, y)| *y)
+        let moving_average_data = if self.show_moving_average {
+            moving_average(&data, self.options.moving_average_window())
+        } else {
+            Vec::new()
+        };
+        let ratio_segments = ratio_keys
+            .map(|(a, b)| self.ratio_chart_segments(a, b))
+            .unwrap_or_default();
+        let color = self.theme.color_for_key(
+            self.chart_lock
+                .as_ref()
+                .map(|lock| lock.key.as_str())
+                .or(self.chart_key())
+                .unwrap_or(""),
+        );
+        let ys = || {
+            data.iter()
+                .chain(compare_data.iter().flat_map(|(_, data)| data))
+                .map(|(_, y)| *y)
+        };
+        let lower_bound = ys()
             .min_by(|a, b| a.total_cmp(b))
             .expect("unreachable")
             .floor();
-        let mut upper_bound = data
-            .iter()
-            .map(|(_, y)| *y)
+        let mut upper_bound = ys()
             .max_by(|a, b| a.total_cmp(b))
             .expect("unreachable")
             .ceil();
@@ -665,7 +2667,115 @@ impl UiState {
             upper_bound = lower_bound + 1.0;
         }
 
+        let graph_type = if self.options.chart_type == ChartType::Scatter {
+            GraphType::Scatter
+        } else {
+            GraphType::Line
+        };
         let x_max = self.options.chart_time_period.get();
+        let area_data = if self.options.chart_type == ChartType::Area && ratio_keys.is_none() {
+            // Zigzags between the bottom and the line at every braille dot column to fill the area.
+            let columns = usize::from(block.inner(area).width) * 2;
+            area_fill_data(&data, x_max as f64, columns)
+                .into_iter()
+                .enumerate()
+                .flat_map(|(i, (x, y))| {
+                    if i % 2 == 0 {
+                        [(x, lower_bound), (x, y)]
+                    } else {
+                        [(x, y), (x, lower_bound)]
+                    }
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let mut datasets = Vec::new();
+        if !area_data.is_empty() {
+            datasets.push(
+                Dataset::default()
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(color).add_modifier(Modifier::DIM))
+                    .data(&area_data),
+            );
+        }
+        if ratio_keys.is_some() {
+            // Each segment is drawn as a separate line so that undefined ratios leave gaps.
+            for segment in &ratio_segments {
+                datasets.push(
+                    Dataset::default()
+                        .marker(Marker::Braille)
+                        .graph_type(graph_type)
+                        .style(Style::default().fg(color))
+                        .data(segment),
+                );
+            }
+        } else {
+            let mut dataset = Dataset::default()
+                .marker(Marker::Braille)
+                .graph_type(graph_type)
+                .style(Style::default().fg(color))
+                .data(&data);
+            if !compare_data.is_empty() {
+                dataset = dataset.name(self.chart_key().unwrap_or("").to_owned());
+            }
+            datasets.push(dataset);
+        }
+        for (key, data) in &compare_data {
+            datasets.push(
+                Dataset::default()
+                    .name(key.to_string())
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(self.theme.color_for_key(key)))
+                    .data(data),
+            );
+        }
+        if self.show_moving_average {
+            datasets.push(
+                Dataset::default()
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(self.theme.moving_average)
+                    .data(&moving_average_data),
+            );
+        }
+
+        if self.options.ascii_chart {
+            let bounds = [lower_bound, upper_bound];
+            let fill = self.options.chart_type == ChartType::Area;
+            render_ascii_chart(
+                f,
+                area,
+                block,
+                &data,
+                x_max as f64,
+                bounds,
+                is_constant,
+                fill,
+            );
+            return;
+        }
+
+        let annotation_data = self
+            .annotation_positions()
+            .into_iter()
+            .filter(|(x, _)| *x <= x_max as f64)
+            .map(|(x, label)| (label, [(x, lower_bound), (x, upper_bound)]))
+            .collect::<Vec<_>>();
+        for (label, data) in &annotation_data {
+            let mut dataset = Dataset::default()
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(self.theme.annotation)
+                .data(data);
+            if !label.is_empty() {
+                dataset = dataset.name(label.to_string());
+            }
+            datasets.push(dataset);
+        }
+
         let y_labels = if is_constant {
             vec![Span::from(format_u64(lower_bound as u64)), Span::from("")]
         } else {
@@ -690,94 +2800,621 @@ impl UiState {
         f.render_widget(chart, area);
     }
 
-    fn chart_data(&self) -> Vec<(f64, f64)> {
-        match self.focus {
-            Focus::AggregatedStats => self.aggregated_chart_data(),
-            Focus::IndividualStats => self.individual_chart_data(),
-        }
+    fn render_histogram(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let Some(key) = self.chart_key() else {
+            f.render_widget(self.make_block("Histogram", None), area);
+            return;
+        };
+        let block = self.make_block(&format!("Histogram of {key:?} ('b' to close)"), None);
+
+        let values = self
+            .visible_connections()
+            .into_iter()
+            .filter_map(|c| c.items.get(key)?.value.as_f64())
+            .collect::<Vec<_>>();
+        if values.is_empty() {
+            f.render_widget(block, area);
+            return;
+        }
+
+        let buckets = self.options.histogram_buckets;
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let bucket_width = (max - min) / buckets as f64;
+        let mut counts = vec![0; buckets];
+        for v in values {
+            let i = if bucket_width > 0.0 {
+                ((v - min) / bucket_width) as usize
+            } else {
+                0
+            };
+            counts[std::cmp::min(i, buckets - 1)] += 1;
+        }
+
+        let bars = counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| {
+                let lower = min + bucket_width * i as f64;
+                Bar::default().value(count).label(Line::from(
+                    self.options.format_context(key).format_number(lower, 0),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let bucket_count = u16::try_from(buckets).unwrap_or(u16::MAX);
+        let bar_width = ((area.width.saturating_sub(2) / bucket_count).saturating_sub(1)).max(1);
+        let chart = BarChart::default()
+            .block(block)
+            .bar_width(bar_width)
+            .bar_style(Style::default().fg(self.theme.color_for_key(key)))
+            .data(BarGroup::default().bars(&bars));
+        f.render_widget(chart, area);
+    }
+
+    fn chart_data(&self) -> Vec<(f64, f64)> {
+        if let Some(lock) = &self.chart_lock {
+            return if let Some(id) = &lock.connection_id {
+                self.connection_key_chart_data(id, &lock.key)
+            } else {
+                self.aggregated_key_chart_data(&lock.key)
+            };
+        }
+        match self.focus {
+            Focus::AggregatedStats => self.aggregated_chart_data(),
+            Focus::Groups => self.group_chart_data(),
+            Focus::IndividualStats => self.individual_chart_data(),
+        }
+    }
+
+    /// Returns the x-axis positions (and labels) of the annotations within the chart window.
+    fn annotation_positions(&self) -> Vec<(f64, &str)> {
+        let (_, mut items) = self.history_window();
+        let Some(start_time) = items.next().map(|s| s.time) else {
+            return Vec::new();
+        };
+        self.annotations
+            .iter()
+            .filter_map(|a| {
+                let x = a.time.duration_since(start_time).ok()?.as_secs_f64();
+                Some((x, a.label.as_str()))
+            })
+            .collect()
+    }
+
+    /// Formats a snapshot time according to `--time-display`.
+    fn format_time(&self, time: SystemTime, timestamp: Duration) -> String {
+        match self.options.time_display {
+            TimeDisplay::Absolute => self
+                .to_display_time(time)
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+            TimeDisplay::Relative => format_relative_time(timestamp),
+        }
+    }
+
+    /// Converts `time` to the local time zone or UTC (`--utc`).
+    fn to_display_time(&self, time: SystemTime) -> chrono::DateTime<chrono::FixedOffset> {
+        if self.options.utc {
+            chrono::DateTime::<chrono::Utc>::from(time).fixed_offset()
+        } else {
+            chrono::DateTime::<chrono::Local>::from(time).fixed_offset()
+        }
+    }
+
+    /// Writes the currently plotted time series to a CSV file in the current directory.
+    fn export_chart_data(&self) -> orfail::Result<String> {
+        let data = self.chart_data();
+        (!data.is_empty()).or_fail_with(|_| "no chart data".to_owned())?;
+
+        let (start, mut items) = self.history_window();
+        let start_time = items.next().or_fail()?.time;
+        let (path, mut file) = create_export_file("sorastats-chart", "csv").or_fail()?;
+        let mut csv = String::new();
+        for annotation in &self.annotations {
+            let timestamp = annotation
+                .time
+                .duration_since(start_time)
+                .map(|x| start + x)
+                .unwrap_or_default();
+            csv.push_str(&format!(
+                "# annotation: {} {}\n",
+                self.format_time(annotation.time, timestamp),
+                annotation.label
+            ));
+        }
+        csv.push_str("time,value\n");
+        for (x, y) in &data {
+            let x = Duration::from_secs_f64(*x);
+            let time = self.format_time(start_time + x, start + x);
+            csv.push_str(&format!("{time},{y}\n"));
+        }
+        file.write_all(csv.as_bytes())
+            .or_fail_with(|e| format!("failed to write {path:?}: {e}"))?;
+        Ok(format!("Exported {} points to {path}", data.len()))
+    }
+
+    /// Writes the charted series to a new JSON file in the current directory.
+    fn export_chart_json(&self) -> orfail::Result<String> {
+        let data = self.chart_data();
+        (!data.is_empty()).or_fail_with(|_| "no chart data".to_owned())?;
+
+        let (key, connection_id) = if let Some(lock) = &self.chart_lock {
+            (lock.key.as_str(), lock.connection_id.as_deref())
+        } else {
+            (self.chart_key().or_fail()?, self.selected_connection_id())
+        };
+        let group = self
+            .selected_group()
+            .filter(|_| self.chart_lock.is_none() && self.focus == Focus::Groups);
+        let (_, mut items) = self.history_window();
+        let start_time = items.next().or_fail()?.time;
+        let points = data
+            .iter()
+            .map(|(x, y)| {
+                let time =
+                    chrono::DateTime::<chrono::Utc>::from(start_time + Duration::from_secs_f64(*x));
+                serde_json::json!({
+                    "t": time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                    "v": y,
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut series = serde_json::json!({ "key": key });
+        if let Some(id) = connection_id {
+            series["connection_id"] = id.into();
+        }
+        if let Some(group) = group {
+            series["group"] = group.into();
+        }
+        series["points"] = points.into();
+
+        let json = serde_json::to_string_pretty(&series).or_fail()?;
+        let (path, mut file) = create_export_file("sorastats-chart", "json").or_fail()?;
+        file.write_all(json.as_bytes())
+            .or_fail_with(|e| format!("failed to write {path:?}: {e}"))?;
+        Ok(format!("Exported {} points to {path}", data.len()))
+    }
+
+    /// Writes the retained history to a new record file in the current directory.
+    fn export_history(&self) -> orfail::Result<String> {
+        let (path, file) = create_export_file("sorastats-history", "jsonl").or_fail()?;
+        let mut recorder = Recorder::new(file, RecordMetadata::new(&self.options), Duration::MAX);
+
+        // Snapshots without connections (e.g., the initial placeholder in realtime mode) are skipped.
+        let mut count = 0;
+        for stats in self.history.iter().filter(|s| !s.connections.is_empty()) {
+            let item = RecordItem {
+                time: stats.time,
+                values: stats.connections.values().map(|c| c.raw.clone()).collect(),
+            };
+            recorder.write_item(&item).or_fail()?;
+            count += 1;
+        }
+        recorder.flush().or_fail()?;
+        Ok(format!("Exported {count} snapshots to {path}"))
+    }
+
+    fn group_chart_data(&self) -> Vec<(f64, f64)> {
+        let (Some(key), Some(group_key)) = (self.chart_key(), &self.options.group_by) else {
+            return Vec::new();
+        };
+        let Some(group) = self.selected_group() else {
+            return self.aggregated_chart_data();
+        };
+
+        let (start, items) = self.history_window();
+        items
+            .filter_map(|stats| {
+                let x = (stats.timestamp - start).as_secs_f64();
+                stats
+                    .groups(group_key)
+                    .get(&group)
+                    .and_then(|g| g.aggregated.get(key))
+                    .and_then(|y| y.delta_per_sec)
+                    .map(|y| (x, y))
+            })
+            .collect()
+    }
+
+    fn individual_chart_data(&self) -> Vec<(f64, f64)> {
+        let (key, id) =
+            if let (Some(key), Some(id)) = (self.chart_key(), self.selected_connection_id()) {
+                (key, id)
+            } else {
+                return Vec::new();
+            };
+        self.connection_key_chart_data(id, key)
+    }
+
+    fn connection_key_chart_data(&self, id: &str, key: &str) -> Vec<(f64, f64)> {
+        let (start, items) = self.history_window();
+        items
+            .filter_map(|stats| {
+                let x = (stats.timestamp - start).as_secs_f64();
+                stats
+                    .connections
+                    .get(id)
+                    .and_then(|c| c.items.get(key))
+                    .and_then(|y| y.delta_per_sec)
+                    .map(|y| (x, y))
+            })
+            .collect()
+    }
+
+    fn aggregated_chart_data(&self) -> Vec<(f64, f64)> {
+        if let Some((a, b)) = self.ratio_keys() {
+            return self.ratio_chart_segments(a, b).concat();
+        }
+        let key = if let Some(key) = self.chart_key() {
+            key
+        } else {
+            return Vec::new();
+        };
+        self.aggregated_key_chart_data(key)
+    }
+
+    fn aggregated_key_chart_data(&self, key: &str) -> Vec<(f64, f64)> {
+        let (start, items) = self.history_window();
+        items
+            .filter_map(|stats| {
+                let x = (stats.timestamp - start).as_secs_f64();
+                stats
+                    .aggregated
+                    .get(key)
+                    .and_then(|y| y.delta_per_sec)
+                    .map(|y| (x, y))
+            })
+            .collect()
+    }
+
+    /// Returns the two keys whose ratio is charted (only if 'r' is on and at least two keys are selected).
+    fn ratio_keys(&self) -> Option<(&StatsItemKey, &StatsItemKey)> {
+        if !self.show_ratio || self.focus != Focus::AggregatedStats || self.chart_lock.is_some() {
+            return None;
+        }
+        match self.visible_selected_keys()[..] {
+            [a, b, ..] => Some((a, b)),
+            _ => None,
+        }
+    }
+
+    /// Returns the `a.value_sum / b.value_sum` series split at the points where the ratio is undefined.
+    fn ratio_chart_segments(&self, a: &str, b: &str) -> Vec<Vec<(f64, f64)>> {
+        let (start, items) = self.history_window();
+        let mut segments = vec![Vec::new()];
+        for stats in items {
+            let x = (stats.timestamp - start).as_secs_f64();
+            let value_sum = |key| stats.aggregated.get(key).and_then(|v| v.value_sum);
+            match (value_sum(a), value_sum(b)) {
+                (Some(a), Some(b)) if b != 0.0 => {
+                    segments.last_mut().expect("unreachable").push((x, a / b));
+                }
+                _ if segments.last().is_some_and(|s| !s.is_empty()) => {
+                    segments.push(Vec::new());
+                }
+                _ => {}
+            }
+        }
+        segments.retain(|s| !s.is_empty());
+        segments
+    }
+
+    /// Selects the aggregated stats key that best matches the search query.
+    fn jump_to_key_search_match(&mut self) {
+        let Some(search) = &self.key_search else {
+            return;
+        };
+        let matches = self
+            .aggregated_items()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, (k, _))| Some((key_match_score(&search.query, k, search.fuzzy)?, i)))
+            .collect::<Vec<_>>();
+        let match_count = matches.len();
+        // Prefers the upper row among the ones with the same score.
+        let best = matches
+            .into_iter()
+            .max_by(|(s0, i0), (s1, i1)| s0.cmp(s1).then_with(|| i1.cmp(i0)))
+            .map(|(_, i)| i);
+        if let Some(i) = best.filter(|_| !search.query.is_empty()) {
+            self.focus = Focus::AggregatedStats;
+            self.aggregated_table_state.select(Some(i));
+        }
+        if let Some(search) = &mut self.key_search {
+            search.match_count = match_count;
+        }
+    }
+
+    fn aggregated_items(&self) -> Vec<(&StatsItemKey, &AggregatedStatsItemValue)> {
+        let (mut pinned, unpinned): (Vec<_>, Vec<_>) = self
+            .latest_stats()
+            .aggregated
+            .filtered_items(&self.options.stats_key_filter)
+            .partition(|(k, _)| self.pinned_keys.contains(*k));
+        if self.split_by_media {
+            let mut unpinned = unpinned;
+            unpinned.sort_by_key(|(k, _)| self.media_section_index(k));
+            pinned.extend(unpinned);
+        } else {
+            pinned.extend(unpinned);
+        }
+        pinned
+    }
+
+    /// Returns the index of the `--media-section` that `key` belongs to (`media_sections.len()` means "other").
+    fn media_section_index(&self, key: &str) -> usize {
+        self.media_sections
+            .iter()
+            .position(|(_, pattern)| pattern.is_match(key))
+            .unwrap_or(self.media_sections.len())
+    }
+
+    fn media_section_name(&self, key: &str) -> &str {
+        self.media_sections
+            .get(self.media_section_index(key))
+            .map_or("other", |(name, _)| name.as_str())
+    }
+
+    fn selected_item_key(&self) -> Option<&str> {
+        self.aggregated_table_state
+            .selected()
+            .and_then(|i| self.aggregated_items().get(i).map(|(k, _)| k.as_str()))
     }
 
-    fn individual_chart_data(&self) -> Vec<(f64, f64)> {
-        let (key, id) = if let (Some(key), Some(id)) =
-            (self.selected_item_key(), self.selected_connection_id())
-        {
-            (key, id)
+    fn chart_key(&self) -> Option<&str> {
+        if let Some(key) = self.visible_selected_keys().first() {
+            Some(key)
         } else {
-            return Vec::new();
-        };
+            self.selected_item_key()
+        }
+    }
 
-        let (start, items) = self.history_window();
-        items
-            .filter_map(|stats| {
-                let x = (stats.timestamp - start).as_secs_f64();
-                stats
-                    .connections
-                    .get(id)
-                    .and_then(|c| c.items.get(key))
-                    .and_then(|y| y.delta_per_sec)
-                    .map(|y| (x, y))
-            })
-            .collect()
+    fn toggle_chart_lock(&mut self) {
+        if self.chart_lock.take().is_some() {
+            return;
+        }
+        let Some(key) = self.chart_key() else {
+            return;
+        };
+        self.chart_lock = Some(ChartLock {
+            key: key.to_owned(),
+            connection_id: self.selected_connection_id().map(|id| id.to_owned()),
+        });
     }
 
-    fn aggregated_chart_data(&self) -> Vec<(f64, f64)> {
-        let key = if let Some(key) = self.selected_item_key() {
-            key
+    fn toggle_compare_key(&mut self) {
+        let Some(key) = self.selected_item_key().map(|k| k.to_owned()) else {
+            return;
+        };
+        if let Some(i) = self.compare_keys.iter().position(|k| *k == key) {
+            self.compare_keys.remove(i);
         } else {
+            self.compare_keys.push(key);
+        }
+    }
+
+    /// Returns the compare keys plotted along with the chart key (only on the aggregated stats chart).
+    fn visible_compare_keys(&self) -> Vec<&StatsItemKey> {
+        if self.focus != Focus::AggregatedStats
+            || self.ratio_keys().is_some()
+            || self.chart_lock.is_some()
+        {
             return Vec::new();
-        };
+        }
+        self.compare_keys
+            .iter()
+            .filter(|k| Some(k.as_str()) != self.chart_key())
+            .filter(|k| self.options.stats_key_filter.is_match(k))
+            .collect()
+    }
 
-        let (start, items) = self.history_window();
-        items
-            .filter_map(|stats| {
-                let x = (stats.timestamp - start).as_secs_f64();
-                stats
-                    .aggregated
-                    .get(key)
-                    .and_then(|y| y.delta_per_sec)
-                    .map(|y| (x, y))
-            })
+    /// Returns the multi-selected keys that match the current stats key filter.
+    fn visible_selected_keys(&self) -> Vec<&StatsItemKey> {
+        self.selected_keys
+            .iter()
+            .filter(|k| self.options.stats_key_filter.is_match(k))
             .collect()
     }
 
-    fn selected_item_key(&self) -> Option<&str> {
-        self.aggregated_table_state.selected().and_then(|i| {
-            self.latest_stats()
-                .aggregated
-                .filtered_items(&self.options.stats_key_filter)
-                .nth(i)
-                .map(|(k, _)| k.as_str())
-        })
+    fn select_item_key(&mut self, key: &str) {
+        if let Some(i) = self
+            .aggregated_items()
+            .iter()
+            .position(|(k, _)| k.as_str() == key)
+        {
+            self.aggregated_table_state.select(Some(i));
+        }
     }
 
     fn selected_connection_id(&self) -> Option<&str> {
-        if self.focus == Focus::AggregatedStats {
+        if self.focus != Focus::IndividualStats {
             return None;
         }
 
         self.individual_table_state.selected().and_then(|i| {
-            self.latest_stats()
-                .connections
-                .iter()
-                .nth(i)
-                .map(|(k, _)| k.as_str())
+            self.visible_connections()
+                .get(i)
+                .map(|c| c.connection_id.as_str())
         })
     }
 
+    /// Returns the name of the group selected in the groups table (`None` means all connections).
+    fn selected_group(&self) -> Option<String> {
+        let group_key = self.options.group_by.as_ref()?;
+        let i = self.groups_table_state.selected()?.checked_sub(1)?;
+        self.latest_stats().groups(group_key).into_keys().nth(i)
+    }
+
+    fn visible_connections(&self) -> Vec<&ConnectionStats> {
+        let connections = self.latest_stats().connections.values();
+        let mut connections = if let (Some(group_key), Some(group)) =
+            (&self.options.group_by, self.selected_group())
+        {
+            connections
+                .filter(|c| {
+                    c.items
+                        .get(group_key)
+                        .map(|item| item.value.to_string())
+                        .unwrap_or_default()
+                        == group
+                })
+                .collect()
+        } else {
+            connections.collect::<Vec<_>>()
+        };
+        let id_cmp = |a: &ConnectionStats, b: &ConnectionStats| {
+            if self.natural_sort {
+                natural_cmp(&a.connection_id, &b.connection_id)
+            } else {
+                a.connection_id.cmp(&b.connection_id)
+            }
+        };
+        let sort = self.options.sort_connections_by.as_ref();
+        let value = |c: &ConnectionStats| {
+            let key = sort.and_then(|s| s.key.as_ref())?;
+            c.items.get(key).and_then(|item| item.value.as_f64())
+        };
+        if connections.iter().any(|c| value(c).is_some()) {
+            let descending = sort.is_some_and(|s| s.descending);
+            connections.sort_by(|a, b| match (value(a), value(b)) {
+                (Some(x), Some(y)) if descending => y.total_cmp(&x).then_with(|| id_cmp(a, b)),
+                (Some(x), Some(y)) => x.total_cmp(&y).then_with(|| id_cmp(a, b)),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => id_cmp(a, b),
+            });
+        } else if sort.is_some_and(|s| s.descending) {
+            connections.sort_by(|a, b| id_cmp(b, a));
+        } else if self.natural_sort {
+            connections.sort_by(|a, b| id_cmp(a, b));
+        }
+        connections
+    }
+
+    fn select_max_delta_row(&mut self) {
+        let focus = self.focus;
+        let Some(i) = self
+            .max_delta_rows
+            .iter()
+            .find(|(f, _)| *f == focus)
+            .map(|(_, i)| *i)
+        else {
+            self.notice = Some("No delta values in the focused table".to_owned());
+            return;
+        };
+        self.focused_table_state().select(Some(i));
+        self.ensure_table_indices_are_in_ranges();
+    }
+
+    /// Handles a mouse click at `position` (returns `true` if the state has changed).
+    fn handle_click(&mut self, position: Position) -> bool {
+        if self.show_delta_detail
+            || self.show_value_types
+            || self.testing_connection_filter.is_some()
+            || self.connection_detail.is_some()
+            || self.alert_log_table_state.is_some()
+        {
+            return false;
+        }
+        let Some((target, area)) = self
+            .click_targets
+            .iter()
+            .find(|(_, area)| area.contains(position))
+            .copied()
+        else {
+            return false;
+        };
+        match target {
+            ClickTarget::Scrubber => {
+                let len = self.history.len();
+                if len == 0 {
+                    return false;
+                }
+                let ratio = f64::from(position.x - area.x) / f64::from(area.width.max(2) - 1);
+                self.end_pos = (ratio * (len - 1) as f64).round() as usize + 1;
+                self.update_totals();
+            }
+            ClickTarget::Table(focus) => {
+                // Skip the top border, the header row and its bottom margin.
+                let Some(line) = (position.y - area.y).checked_sub(3) else {
+                    return false;
+                };
+                if position.y + 1 >= area.bottom() {
+                    return false;
+                }
+                let pinned_count = if focus == Focus::AggregatedStats {
+                    self.aggregated_items()
+                        .iter()
+                        .take_while(|(k, _)| self.pinned_keys.contains(*k))
+                        .count()
+                } else {
+                    0
+                };
+                self.focus = focus;
+                let table = self.focused_table_state();
+                let offset = table.offset();
+                let mut i = offset + usize::from(line);
+                if offset < pinned_count && i > pinned_count {
+                    // There is a blank line after the pinned rows.
+                    i -= 1;
+                }
+                table.select(Some(i));
+            }
+        }
+        self.ensure_table_indices_are_in_ranges();
+        true
+    }
+
+    fn toggle_connection_detail(&mut self) {
+        if self.connection_detail.take().is_some() {
+            return;
+        }
+        let Some(connection_id) = self.selected_connection_id().map(|id| id.to_owned()) else {
+            self.notice =
+                Some("Select a connection in the right table to see its details".to_owned());
+            return;
+        };
+        let mut table_state = TableState::default();
+        table_state.select(Some(0));
+        self.connection_detail = Some(ConnectionDetail {
+            connection_id,
+            table_state,
+        });
+    }
+
+    fn toggle_alert_log(&mut self) {
+        if self.alert_log_table_state.take().is_some() {
+            return;
+        }
+        let mut table_state = TableState::default();
+        table_state.select(Some(0));
+        self.alert_log_table_state = Some(table_state);
+    }
+
+    fn focused_table_state(&mut self) -> &mut TableState {
+        if let Some(state) = &mut self.alert_log_table_state {
+            return state;
+        }
+        if let Some(detail) = &mut self.connection_detail {
+            return &mut detail.table_state;
+        }
+        match self.focus {
+            Focus::AggregatedStats => &mut self.aggregated_table_state,
+            Focus::Groups => &mut self.groups_table_state,
+            Focus::IndividualStats => &mut self.individual_table_state,
+        }
+    }
+
     fn make_block(&self, name: &str, block: Option<Focus>) -> ratatui::widgets::Block<'static> {
         if block == Some(self.focus) {
             Block::default()
                 .borders(Borders::ALL)
-                .title(Span::styled(
-                    name.to_string(),
-                    Style::default().add_modifier(Modifier::BOLD),
-                ))
-                .border_style(Style::default().add_modifier(Modifier::BOLD))
+                .title(Span::styled(name.to_string(), self.theme.title))
+                .border_style(self.theme.focused_border)
         } else {
-            Block::default().borders(Borders::ALL).title(Span::styled(
-                name.to_string(),
-                Style::default().add_modifier(Modifier::BOLD),
-            ))
+            Block::default()
+                .borders(Borders::ALL)
+                .title(Span::styled(name.to_string(), self.theme.title))
+                .border_style(self.theme.border)
         }
     }
 
@@ -796,13 +3433,429 @@ impl UiState {
             self.aggregated_table_state.select(Some(i));
         }
 
-        if self.latest_stats().connection_count() == 0 {
+        if let Some(group_key) = &self.options.group_by {
+            // The first row is for all connections.
+            let n = self.latest_stats().groups(group_key).len() + 1;
+            let i = std::cmp::min(self.groups_table_state.selected().unwrap_or(0), n - 1);
+            self.groups_table_state.select(Some(i));
+        }
+
+        let n = self.visible_connections().len();
+        if n == 0 {
             self.individual_table_state.select(None);
         } else {
-            let n = self.latest_stats().connection_count();
             let i = std::cmp::min(self.individual_table_state.selected().unwrap_or(0), n - 1);
             self.individual_table_state.select(Some(i));
         }
+
+        let n = self
+            .connection_detail
+            .as_ref()
+            .and_then(|d| self.latest_stats().connections.get(&d.connection_id))
+            .map_or(0, |c| c.items.len());
+        if let Some(detail) = &mut self.connection_detail {
+            let i = std::cmp::min(
+                detail.table_state.selected().unwrap_or(0),
+                n.saturating_sub(1),
+            );
+            detail.table_state.select(Some(i));
+        }
+
+        let n = self.alert_log.len();
+        if let Some(state) = &mut self.alert_log_table_state {
+            let i = std::cmp::min(state.selected().unwrap_or(0), n.saturating_sub(1));
+            state.select(Some(i));
+        }
+    }
+}
+
+/// Formats a duration since the first snapshot like "+00:01:23.450".
+fn format_relative_time(timestamp: Duration) -> String {
+    let secs = timestamp.as_secs();
+    format!(
+        "+{:02}:{:02}:{:02}.{:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        timestamp.subsec_millis()
+    )
+}
+
+/// Returns how well `key` matches `query` (higher is better) or `None` if it does not match at all.
+///
+/// In fuzzy mode, the characters of the query only need to appear in the key in order
+/// (e.g., "rpktlost" matches "rtp.packets_lost"), and consecutive matches and matches at the start of a key segment
+/// score higher. Matching is case-insensitive and shorter keys win ties.
+fn key_match_score(query: &str, key: &str, fuzzy: bool) -> Option<i64> {
+    let query = query.to_lowercase().chars().collect::<Vec<_>>();
+    let key = key.to_lowercase().chars().collect::<Vec<_>>();
+    let is_segment_start = |i: usize| i == 0 || matches!(key[i - 1], '.' | '_' | '-');
+
+    let mut score = 0;
+    if fuzzy {
+        let mut i = 0;
+        let mut prev = None;
+        for c in &query {
+            i += key[i..].iter().position(|k| k == c)?;
+            score += 1;
+            if prev.is_some_and(|prev| prev + 1 == i) {
+                score += 5;
+            }
+            if is_segment_start(i) {
+                score += 3;
+            }
+            prev = Some(i);
+            i += 1;
+        }
+    } else {
+        let start =
+            (0..=key.len().saturating_sub(query.len())).find(|&i| key[i..].starts_with(&query))?;
+        if is_segment_start(start) {
+            score += 1;
+        }
+    }
+    Some(score * 1000 - key.len() as i64)
+}
+
+/// Compares strings treating digit sequences as numbers (e.g., "conn-2" < "conn-10").
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    fn chunks(s: &str) -> impl '_ + Iterator<Item = &str> {
+        let mut rest = s;
+        std::iter::from_fn(move || {
+            let first = rest.chars().next()?;
+            let n = rest
+                .find(|c: char| c.is_ascii_digit() != first.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let (chunk, remaining) = rest.split_at(n);
+            rest = remaining;
+            Some(chunk)
+        })
+    }
+
+    let mut a_chunks = chunks(a);
+    let mut b_chunks = chunks(b);
+    loop {
+        let ordering = match (a_chunks.next(), b_chunks.next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return std::cmp::Ordering::Less,
+            (Some(_), None) => return std::cmp::Ordering::Greater,
+            (Some(x), Some(y))
+                if x.starts_with(|c: char| c.is_ascii_digit())
+                    && y.starts_with(|c: char| c.is_ascii_digit()) =>
+            {
+                let x = x.trim_start_matches('0');
+                let y = y.trim_start_matches('0');
+                x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+            }
+            (Some(x), Some(y)) => x.cmp(y),
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+}
+
+/// Linearly interpolates `data` at `columns` evenly spaced x positions (those outside the data range are skipped).
+fn area_fill_data(data: &[(f64, f64)], x_max: f64, columns: usize) -> Vec<(f64, f64)> {
+    let (Some(&(x_first, _)), Some(&(x_last, _))) = (data.first(), data.last()) else {
+        return Vec::new();
+    };
+    if columns < 2 {
+        return Vec::new();
+    }
+
+    let mut points = Vec::new();
+    let mut i = 0;
+    for column in 0..columns {
+        let x = x_max * column as f64 / (columns - 1) as f64;
+        if x < x_first || x > x_last {
+            continue;
+        }
+        while i + 1 < data.len() && data[i + 1].0 < x {
+            i += 1;
+        }
+        let (x0, y0) = data[i];
+        let y = match data.get(i + 1) {
+            Some(&(x1, y1)) if x1 > x0 => y0 + (y1 - y0) * ((x - x0) / (x1 - x0)).clamp(0.0, 1.0),
+            _ => y0,
+        };
+        points.push((x, y));
+    }
+    points
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_ascii_chart(
+    f: &mut Frame,
+    area: ratatui::layout::Rect,
+    block: Block,
+    data: &[(f64, f64)],
+    x_max: f64,
+    [lower_bound, upper_bound]: [f64; 2],
+    is_constant: bool,
+    fill: bool,
+) {
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let lower_label = format_u64(lower_bound as u64);
+    let upper_label = if is_constant {
+        String::new()
+    } else {
+        format_u64(upper_bound as u64)
+    };
+    let label_width = lower_label.len().max(upper_label.len());
+    let height = usize::from(inner.height).saturating_sub(2);
+    let width = usize::from(inner.width).saturating_sub(label_width + 1);
+    if height == 0 || width < 2 {
+        return;
+    }
+
+    let row_of = |y: f64| {
+        let ratio = ((y - lower_bound) / (upper_bound - lower_bound)).clamp(0.0, 1.0);
+        height - 1 - (ratio * (height - 1) as f64).round() as usize
+    };
+    let mut grid = vec![vec![' '; width]; height];
+    if fill {
+        for (x, y) in area_fill_data(data, x_max, width) {
+            let column = ((x / x_max) * (width - 1) as f64).round() as usize;
+            for cells in &mut grid[row_of(y)..] {
+                cells[column] = ':';
+            }
+        }
+    }
+
+    // Plots the largest value in each column.
+    let mut column_values = vec![None::<f64>; width];
+    for &(x, y) in data {
+        let column = ((x / x_max) * (width - 1) as f64).round();
+        if !(0.0..width as f64).contains(&column) {
+            continue;
+        }
+        let value = &mut column_values[column as usize];
+        *value = Some(value.map_or(y, |v| v.max(y)));
+    }
+    for (column, y) in column_values.into_iter().enumerate() {
+        let Some(y) = y else {
+            continue;
+        };
+        grid[row_of(y)][column] = '*';
+    }
+
+    let mut lines = grid
+        .into_iter()
+        .enumerate()
+        .map(|(row, cells)| {
+            let label = if row == 0 {
+                upper_label.as_str()
+            } else if row == height - 1 {
+                lower_label.as_str()
+            } else {
+                ""
+            };
+            let cells = cells.into_iter().collect::<String>();
+            Line::from(format!("{label:>label_width$}|{cells}"))
+        })
+        .collect::<Vec<_>>();
+    lines.push(Line::from(format!(
+        "{:label_width$}{}",
+        "",
+        "-".repeat(width + 1)
+    )));
+    let x_max_label = format!("{x_max}s");
+    lines.push(Line::from(format!(
+        "{:label_width$} 0s{x_max_label:>pad$}",
+        "",
+        pad = width.saturating_sub(2)
+    )));
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+/// Returns the index of the largest value (`None` values are ignored).
+fn max_index(values: impl Iterator<Item = Option<f64>>) -> Option<usize> {
+    values
+        .enumerate()
+        .filter_map(|(i, v)| Some((i, v?)))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+}
+
+fn format_group_name(name: &str) -> String {
+    if name.is_empty() {
+        "(none)".to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+/// The compact layout is used regardless of `--compact` if the terminal is lower than this.
+const COMPACT_LAYOUT_AUTO_HEIGHT: u16 = 20;
+
+/// Below this size, only a message asking to enlarge the terminal is rendered.
+const MIN_TERMINAL_WIDTH: u16 = 40;
+const MIN_TERMINAL_HEIGHT: u16 = 10;
+
+// Aggregated stats keys shown as the TX / RX rates in the compact status bar.
+const COMPACT_TX_KEY: &str = "total_sent_byte_size";
+const COMPACT_RX_KEY: &str = "total_received_byte_size";
+
+const BIG_TEXT_HEIGHT: usize = 5;
+
+fn big_glyph(c: char) -> Option<[&'static str; BIG_TEXT_HEIGHT]> {
+    let glyph = match c {
+        '0' => ["███", "█ █", "█ █", "█ █", "███"],
+        '1' => [" █ ", "██ ", " █ ", " █ ", "███"],
+        '2' => ["███", "  █", "███", "█  ", "███"],
+        '3' => ["███", "  █", "███", "  █", "███"],
+        '4' => ["█ █", "█ █", "███", "  █", "  █"],
+        '5' => ["███", "█  ", "███", "  █", "███"],
+        '6' => ["███", "█  ", "███", "█ █", "███"],
+        '7' => ["███", "  █", "  █", "  █", "  █"],
+        '8' => ["███", "█ █", "███", "█ █", "███"],
+        '9' => ["███", "█ █", "███", "  █", "███"],
+        ',' => ["  ", "  ", "  ", " █", "█ "],
+        '.' => [" ", " ", " ", " ", "█"],
+        '-' => ["   ", "   ", "███", "   ", "   "],
+        _ => return None,
+    };
+    Some(glyph)
+}
+
+/// Renders the given text with large glyphs if possible (otherwise, returns the text as is).
+fn big_text_lines(text: &str) -> Vec<Line<'static>> {
+    let Some(glyphs) = text.chars().map(big_glyph).collect::<Option<Vec<_>>>() else {
+        return vec![Line::from(text.to_owned())];
+    };
+    (0..BIG_TEXT_HEIGHT)
+        .map(|row| {
+            let line = glyphs
+                .iter()
+                .map(|glyph| glyph[row])
+                .collect::<Vec<_>>()
+                .join(" ");
+            Line::styled(line, Style::default().add_modifier(Modifier::BOLD))
+        })
+        .collect()
+}
+
+fn moving_average(data: &[(f64, f64)], window: f64) -> Vec<(f64, f64)> {
+    let mut start = 0;
+    let mut sum = 0.0;
+    data.iter()
+        .enumerate()
+        .map(|(i, &(x, y))| {
+            sum += y;
+            while data[start].0 <= x - window {
+                sum -= data[start].1;
+                start += 1;
+            }
+            (x, sum / (i + 1 - start) as f64)
+        })
+        .collect()
+}
+
+/// Creates a new file named after the current time in the current directory
+/// (a `-N` suffix is appended if the name is already taken, so that earlier exports are not overwritten).
+fn create_export_file(prefix: &str, extension: &str) -> orfail::Result<(String, std::fs::File)> {
+    let base = format!("{prefix}-{}", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    for i in 1.. {
+        let path = if i == 1 {
+            format!("{base}.{extension}")
+        } else {
+            format!("{base}-{i}.{extension}")
+        };
+        match std::fs::File::create_new(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+            Err(e) => {
+                return Err(orfail::Failure::new(format!(
+                    "failed to create {path:?}: {e}"
+                )))
+            }
+        }
+    }
+    unreachable!()
+}
+
+/// Popup listing all the stats items of a connection.
+#[derive(Debug)]
+struct ConnectionDetail {
+    connection_id: ConnectionId,
+    table_state: TableState,
+}
+
+/// Series pinned to the chart regardless of the focused table.
+#[derive(Debug)]
+struct ChartLock {
+    key: StatsItemKey,
+
+    // `None` means the aggregated series.
+    connection_id: Option<ConnectionId>,
+}
+
+/// Marker on the chart added by the user.
+#[derive(Debug)]
+struct Annotation {
+    time: SystemTime,
+    label: String,
+}
+
+/// Number of connections added / removed by the last poll that changed the connection set.
+#[derive(Debug)]
+struct ConnectionChurn {
+    added: usize,
+    removed: usize,
+    time: Instant,
+}
+
+const CONNECTION_CHURN_DISPLAY_DURATION: Duration = Duration::from_secs(3);
+
+/// Cumulative deltas of the aggregated stats over the session.
+#[derive(Debug, Default)]
+struct SessionTotals {
+    values: BTreeMap<StatsItemKey, f64>,
+
+    // For replay mode
+    pos: usize,
+    base_pos: usize,
+}
+
+impl SessionTotals {
+    fn add(&mut self, aggregated: &AggregatedStats, sign: f64) {
+        for (k, item) in aggregated.iter() {
+            if let Some(delta) = item.delta {
+                *self.values.entry(k.clone()).or_default() += delta * sign;
+            }
+        }
+    }
+
+    fn format_total(&self, key: &str, ctx: FormatContext) -> String {
+        if let Some(v) = self.values.get(key) {
+            ctx.format_number(*v, 0)
+        } else {
+            String::new()
+        }
+    }
+}
+
+#[derive(Debug)]
+struct KeySearch {
+    query: String,
+    fuzzy: bool,
+    match_count: usize,
+
+    // Restored when the search is cancelled.
+    origin_focus: Focus,
+    origin_selected: Option<usize>,
+}
+
+impl KeySearch {
+    fn prompt(&self) -> String {
+        format!(
+            "[SEARCH KEY ({}, matches: {}, Tab to switch, Enter / Esc to finish)] ",
+            if self.fuzzy { "fuzzy" } else { "substring" },
+            self.match_count
+        )
     }
 }
 
@@ -824,3 +3877,141 @@ impl EditingStatsKeyFilter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser as _;
+    use ratatui::backend::TestBackend;
+
+    impl UiState {
+        /// Makes a state that shows `history` as a fully replayed record (no poller or channel is involved).
+        fn with_history(options: Options, history: Vec<Stats>) -> Self {
+            let mut ui = Self::new(options);
+            ui.realtime = false;
+            ui.eof = true;
+            ui.end_pos = history.len();
+            ui.history = history.into();
+            ui.update_totals();
+            ui.ensure_table_indices_are_in_ranges();
+            ui
+        }
+
+        fn render_to_lines(&mut self, width: u16, height: u16) -> Vec<String> {
+            let mut terminal = ratatui::Terminal::new(TestBackend::new(width, height)).unwrap();
+            terminal.draw(|f| self.render(f)).unwrap();
+            let buffer = terminal.backend().buffer();
+            (0..height)
+                .map(|y| {
+                    (0..width)
+                        .map(|x| buffer[(x, y)].symbol())
+                        .collect::<String>()
+                })
+                .collect()
+        }
+    }
+
+    fn options(args: &[&str]) -> Options {
+        let args = ["sorastats", "test.jsonl", "--time-display", "relative"]
+            .iter()
+            .chain(args);
+        Options::parse_from(args)
+    }
+
+    /// Builds snapshots taken every second, where `bytes_sent[i][j]` is the value of the j-th connection at the i-th snapshot.
+    fn history(bytes_sent: &[&[u64]]) -> Vec<Stats> {
+        let mut history: Vec<Stats> = Vec::new();
+        for (i, values) in bytes_sent.iter().enumerate() {
+            let timestamp = Duration::from_secs(i as u64);
+            let time = SystemTime::UNIX_EPOCH + timestamp;
+            let prev = history.last().cloned().unwrap_or_else(Stats::empty);
+            let connections = values
+                .iter()
+                .enumerate()
+                .map(|(j, v)| {
+                    let json =
+                        serde_json::json!({"connection_id": format!("conn-{j}"), "bytes_sent": v});
+                    ConnectionStats::new(json, &prev, time).unwrap()
+                })
+                .collect();
+            history.push(Stats::new(time, timestamp, connections));
+        }
+        history
+    }
+
+    fn find_line<'a>(lines: &'a [String], pattern: &str) -> &'a str {
+        lines
+            .iter()
+            .find(|line| line.contains(pattern))
+            .unwrap_or_else(|| panic!("{pattern:?} not found in:\n{}", lines.join("\n")))
+    }
+
+    #[test]
+    fn render_aggregated_stats_table() {
+        let history = history(&[&[100, 200, 300], &[200, 400, 600]]);
+        let mut ui = UiState::with_history(options(&[]), history);
+        let lines = ui.render_to_lines(160, 40);
+
+        let row = find_line(&lines, "1> bytes_sent");
+        let cells = row.split_whitespace().collect::<Vec<_>>();
+        assert!(cells.contains(&"1,200"), "{row}");
+        assert!(cells.contains(&"600"), "{row}");
+
+        let row = find_line(&lines, "Connections:");
+        assert!(row.contains("Connections:     3"), "{row}");
+    }
+
+    #[test]
+    fn render_aggregated_stats_table_as_average() {
+        let history = history(&[&[100, 200, 300], &[200, 400, 600]]);
+        let mut ui = UiState::with_history(options(&[]), history);
+        ui.show_average = true;
+        let lines = ui.render_to_lines(160, 40);
+
+        assert!(find_line(&lines, "Avg").contains("Key"));
+        let row = find_line(&lines, "1> bytes_sent");
+        assert!(row.split_whitespace().any(|cell| cell == "400"), "{row}");
+    }
+
+    #[test]
+    fn chart_bounds_span_the_plotted_deltas() {
+        let history = history(&[&[0, 0], &[100, 500], &[400, 1100]]);
+        let mut ui = UiState::with_history(options(&[]), history);
+        assert_eq!(ui.chart_data(), vec![(1.0, 600.0), (2.0, 900.0)]);
+
+        let lines = ui.render_to_lines(160, 40);
+        let chart_title = lines
+            .iter()
+            .position(|line| line.contains("Delta/s Chart of \"bytes_sent\""))
+            .expect("no chart");
+        let y_labels = lines[chart_title + 1..]
+            .iter()
+            .take_while(|line| !line.contains('└'))
+            .filter_map(|line| {
+                let (_, chart) = line.split_once("││")?;
+                let (label, _) = chart.split_once('│')?;
+                Some(label.trim().to_owned())
+            })
+            .filter(|label| !label.is_empty())
+            .collect::<Vec<_>>();
+        assert_eq!(y_labels, ["900", "600"]);
+    }
+
+    #[test]
+    fn chart_bounds_of_constant_deltas() {
+        let history = history(&[&[0], &[100], &[200]]);
+        let mut ui = UiState::with_history(options(&[]), history);
+        assert_eq!(ui.chart_data(), vec![(1.0, 100.0), (2.0, 100.0)]);
+
+        let lines = ui.render_to_lines(160, 40);
+        let chart_title = lines
+            .iter()
+            .position(|line| line.contains("Delta/s Chart of \"bytes_sent\""))
+            .expect("no chart");
+        let labels = lines[chart_title + 1..]
+            .iter()
+            .filter(|line| line.contains("│100"))
+            .count();
+        assert_eq!(labels, 1);
+    }
+}